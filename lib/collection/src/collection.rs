@@ -0,0 +1,44 @@
+use std::fmt;
+
+use segment::entry::entry_point::OperationError;
+use segment::types::{PointIdType, SeqNumberType};
+
+pub type OperationResult<T> = Result<T, UpdateError>;
+
+#[derive(Debug)]
+pub enum UpdateError {
+    NotFound { missed_point_id: PointIdType },
+    ServiceError { error: String },
+    /// An operation specified an `expected_version` for `point_id` that didn't match
+    /// the point's current version, so the mutation was rejected rather than silently
+    /// overwriting a concurrent change.
+    VersionConflict { point_id: PointIdType, expected: SeqNumberType, actual: SeqNumberType },
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UpdateError::NotFound { missed_point_id } => write!(f, "point {} not found", missed_point_id),
+            UpdateError::ServiceError { error } => write!(f, "service error: {}", error),
+            UpdateError::VersionConflict { point_id, expected, actual } => write!(
+                f,
+                "point {} is at version {} but expected {}",
+                point_id, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
+
+impl From<OperationError> for UpdateError {
+    fn from(err: OperationError) -> Self {
+        match err {
+            OperationError::PointIdError { missed_point_id } => UpdateError::NotFound { missed_point_id },
+            OperationError::ServiceError { description } => UpdateError::ServiceError { error: description },
+            OperationError::VersionConflict { point_id, expected, actual } => {
+                UpdateError::VersionConflict { point_id, expected, actual }
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod collection;
+pub mod operations;
+pub mod segment_manager;
@@ -0,0 +1,14 @@
+pub mod payload_ops;
+pub mod point_ops;
+pub mod types;
+
+use serde::{Deserialize, Serialize};
+
+use payload_ops::PayloadOps;
+use point_ops::PointOps;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CollectionUpdateOperations {
+    PointOperation(PointOps),
+    PayloadOperation(PayloadOps),
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use segment::types::{PayloadKeyType, PayloadType, PointIdType, SeqNumberType};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PayloadVariant<T> {
+    Value(T),
+    List(Vec<T>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PayloadInterface {
+    Keyword(PayloadVariant<String>),
+    Integer(PayloadVariant<i64>),
+    Float(PayloadVariant<f64>),
+}
+
+impl PayloadInterface {
+    pub fn to_payload(&self) -> PayloadType {
+        match self {
+            PayloadInterface::Keyword(PayloadVariant::Value(v)) => PayloadType::Keyword(vec![v.clone()]),
+            PayloadInterface::Keyword(PayloadVariant::List(v)) => PayloadType::Keyword(v.clone()),
+            PayloadInterface::Integer(PayloadVariant::Value(v)) => PayloadType::Integer(vec![*v]),
+            PayloadInterface::Integer(PayloadVariant::List(v)) => PayloadType::Integer(v.clone()),
+            PayloadInterface::Float(PayloadVariant::Value(v)) => PayloadType::Float(vec![*v]),
+            PayloadInterface::Float(PayloadVariant::List(v)) => PayloadType::Float(v.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PayloadOps {
+    SetPayload {
+        collection: String,
+        payload: HashMap<PayloadKeyType, PayloadInterface>,
+        points: Vec<PointIdType>,
+        /// Per-point version the caller last read, if it wants the edit rejected with
+        /// `UpdateError::VersionConflict` instead of applied when a point's current
+        /// version differs.
+        #[serde(default)]
+        expected_versions: Option<HashMap<PointIdType, SeqNumberType>>,
+    },
+    DeletePayload {
+        collection: String,
+        points: Vec<PointIdType>,
+        keys: Vec<PayloadKeyType>,
+        #[serde(default)]
+        expected_versions: Option<HashMap<PointIdType, SeqNumberType>>,
+    },
+    ClearPayload {
+        collection: String,
+        points: Vec<PointIdType>,
+    },
+    WipePayload {
+        collection: String,
+    },
+}
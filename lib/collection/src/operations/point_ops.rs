@@ -0,0 +1,24 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::operations::types::VectorType;
+use segment::types::{PointIdType, SeqNumberType};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PointOps {
+    UpsertPoints {
+        collection: String,
+        ids: Vec<PointIdType>,
+        vectors: Vec<VectorType>,
+        /// Per-point version the caller last read, if it wants the upsert rejected
+        /// with `UpdateError::VersionConflict` instead of applied when a point's
+        /// current version (0 for a point that doesn't exist yet) differs.
+        #[serde(default)]
+        expected_versions: Option<HashMap<PointIdType, SeqNumberType>>,
+    },
+    DeletePoints {
+        collection: String,
+        ids: Vec<PointIdType>,
+    },
+}
@@ -0,0 +1,3 @@
+use segment::types::VectorElementType;
+
+pub type VectorType = Vec<VectorElementType>;
@@ -0,0 +1,203 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::collection::OperationResult;
+use crate::operations::CollectionUpdateOperations;
+use crate::segment_manager::segment_managers::SegmentUpdater;
+use crate::segment_manager::task_store::{Task, TaskFilter, TaskId, TaskStatus, TaskStore};
+
+/// Wraps a `SegmentUpdater` to turn `update()` into an enqueue operation: `submit()`
+/// returns a task id immediately, and a single background worker pulls tasks in
+/// submission order and runs the wrapped updater, persisting status transitions as it
+/// goes so callers can poll for completion instead of holding a request open.
+pub struct AsyncSegmentUpdater {
+    store: Arc<TaskStore>,
+    sender: Sender<TaskId>,
+    /// Guards the id fetch-and-increment together with the store `enqueue` and channel
+    /// `send` that follow it, so ids reach the worker's single channel in the same order
+    /// they're allocated -- an `AtomicU64` alone only serializes the increment itself,
+    /// leaving a window where a thread that grabbed a lower id can still lose the race
+    /// to enqueue/send it, and the worker (which applies "higher op_num wins") would
+    /// then silently treat the lower one as a stale no-op.
+    next_id: Mutex<TaskId>,
+}
+
+impl AsyncSegmentUpdater {
+    pub fn new(updater: Arc<dyn SegmentUpdater + Send + Sync>, store: Arc<TaskStore>) -> Self {
+        let (sender, receiver) = mpsc::channel::<TaskId>();
+
+        // Resume any tasks that were still enqueued when the process last stopped,
+        // oldest first so op_num ordering between the queue and segment application
+        // stays consistent. Ids (and therefore op_nums) start at 1, not 0: a segment's
+        // "point never written" sentinel version is 0, and op_num <= current_version is
+        // treated as a stale no-op, so op_num 0 could never apply to a fresh point.
+        //
+        // `next_id` must be derived from the max id across *all* persisted tasks, not
+        // just the pending ones -- if the process restarted with nothing left enqueued
+        // (the common case: everything before the crash already completed), resuming
+        // from `pending_ids()` alone would reset to 1 and reuse ids, and therefore
+        // op_nums, already stamped on existing points. Every later write would then be
+        // silently rejected as a stale replay.
+        let mut pending = store.pending_ids();
+        pending.sort_unstable();
+        let next_id = store.max_task_id().map_or(1, |id| id + 1);
+        for id in &pending {
+            sender.send(*id).expect("worker thread outlives the sender");
+        }
+
+        let worker_store = store.clone();
+        thread::spawn(move || {
+            for task_id in receiver {
+                let task = match worker_store.get(task_id) {
+                    Some(task) => task,
+                    None => continue,
+                };
+                let _ = worker_store.set_status(task_id, TaskStatus::Processing);
+                let status = match updater.update(task_id, &task.operation) {
+                    Ok(updated) => TaskStatus::Succeeded { updated },
+                    Err(error) => TaskStatus::Failed { error: format!("{:?}", error) },
+                };
+                let _ = worker_store.set_status(task_id, status);
+            }
+        });
+
+        AsyncSegmentUpdater { store, sender, next_id: Mutex::new(next_id) }
+    }
+
+    /// Enqueues `operation` and returns its task id without waiting for it to run.
+    /// The returned id doubles as the op_num the operation is eventually applied with.
+    pub fn submit(&self, operation: CollectionUpdateOperations) -> OperationResult<TaskId> {
+        // Allocating the id and handing it to the store/channel must be indivisible,
+        // or two concurrent submitters can interleave such that the higher id is
+        // enqueued and sent before the lower one, and the worker applies them out of
+        // order.
+        let mut next_id = self.next_id.lock().unwrap();
+        let id = *next_id;
+        *next_id += 1;
+        self.store.enqueue(id, operation)?;
+        self.sender.send(id).expect("worker thread outlives the sender");
+        Ok(id)
+    }
+
+    pub fn get_task(&self, id: TaskId) -> Option<Task> {
+        self.store.get(id)
+    }
+
+    pub fn list_tasks(&self, filter: &TaskFilter) -> Vec<Task> {
+        self.store.list(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use crate::operations::point_ops::PointOps;
+    use crate::segment_manager::fixtures::build_test_holder;
+    use crate::segment_manager::merge_optimizer::MergeThresholds;
+    use crate::segment_manager::simple_segment_updater::SimpleSegmentUpdater;
+
+    fn await_completion(async_updater: &AsyncSegmentUpdater, task_id: TaskId) -> TaskStatus {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            let task = async_updater.get_task(task_id).unwrap();
+            if !matches!(task.status, TaskStatus::Enqueued | TaskStatus::Processing) || Instant::now() > deadline {
+                return task.status;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_submitted_task_runs_to_completion() {
+        let segments = build_test_holder();
+        let wal_path = std::env::temp_dir().join(format!("async_updater_test_{:?}.wal", std::thread::current().id()));
+        let updater: Arc<dyn SegmentUpdater + Send + Sync> =
+            Arc::new(SimpleSegmentUpdater::new(segments, MergeThresholds::default(), 1, &wal_path).unwrap());
+
+        let store_path = std::env::temp_dir().join(format!("async_updater_test_{:?}.tasks.json", std::thread::current().id()));
+        let _ = std::fs::remove_file(&store_path);
+        let store = Arc::new(TaskStore::open(&store_path).unwrap());
+
+        let async_updater = AsyncSegmentUpdater::new(updater, store);
+
+        // Insert a brand-new point first -- its version starts at the sentinel 0, so
+        // the first task id is new enough to apply regardless of what it is.
+        let insert_id = async_updater
+            .submit(CollectionUpdateOperations::PointOperation(PointOps::UpsertPoints {
+                collection: "".to_string(),
+                ids: vec![999],
+                vectors: vec![vec![1., 1., 1., 1.]],
+                expected_versions: None,
+            }))
+            .unwrap();
+        assert_eq!(await_completion(&async_updater, insert_id), TaskStatus::Succeeded { updated: 1 });
+
+        // Now delete it through a second task -- its id is higher than the point's
+        // version from the insert above, so the delete isn't mistaken for a stale replay.
+        let delete_id = async_updater
+            .submit(CollectionUpdateOperations::PointOperation(PointOps::DeletePoints {
+                collection: "".to_string(),
+                ids: vec![999],
+            }))
+            .unwrap();
+        assert_eq!(await_completion(&async_updater, delete_id), TaskStatus::Succeeded { updated: 1 });
+
+        let listed = async_updater.list_tasks(&TaskFilter::default());
+        assert_eq!(listed.len(), 2);
+
+        std::fs::remove_file(&store_path).unwrap();
+        let _ = std::fs::remove_file(&wal_path);
+    }
+
+    #[test]
+    fn test_next_id_after_restart_with_no_pending_tasks_does_not_reuse_ids() {
+        let segments = build_test_holder();
+        let wal_path = std::env::temp_dir().join(format!("async_updater_restart_test_{:?}.wal", std::thread::current().id()));
+        let store_path = std::env::temp_dir().join(format!("async_updater_restart_test_{:?}.tasks.json", std::thread::current().id()));
+        let _ = std::fs::remove_file(&store_path);
+
+        let first_id = {
+            let updater: Arc<dyn SegmentUpdater + Send + Sync> =
+                Arc::new(SimpleSegmentUpdater::new(segments.clone(), MergeThresholds::default(), 1, &wal_path).unwrap());
+            let store = Arc::new(TaskStore::open(&store_path).unwrap());
+            let async_updater = AsyncSegmentUpdater::new(updater, store);
+
+            let id = async_updater
+                .submit(CollectionUpdateOperations::PointOperation(PointOps::UpsertPoints {
+                    collection: "".to_string(),
+                    ids: vec![999],
+                    vectors: vec![vec![1., 1., 1., 1.]],
+                    expected_versions: None,
+                }))
+                .unwrap();
+            assert_eq!(await_completion(&async_updater, id), TaskStatus::Succeeded { updated: 1 });
+            id
+        }; // async_updater dropped here -- by now nothing is left `Enqueued`.
+
+        // Restart over the same store: `pending_ids()` is empty, so `next_id` must come
+        // from `max_task_id()` instead, or this second task would reuse `first_id` as
+        // its op_num -- which is <= point 999's current version, so the upsert would be
+        // silently rejected as a stale replay instead of actually applying.
+        let updater: Arc<dyn SegmentUpdater + Send + Sync> =
+            Arc::new(SimpleSegmentUpdater::new(segments, MergeThresholds::default(), 1, &wal_path).unwrap());
+        let store = Arc::new(TaskStore::open(&store_path).unwrap());
+        let async_updater = AsyncSegmentUpdater::new(updater, store);
+
+        let second_id = async_updater
+            .submit(CollectionUpdateOperations::PointOperation(PointOps::UpsertPoints {
+                collection: "".to_string(),
+                ids: vec![999],
+                vectors: vec![vec![2., 2., 2., 2.]],
+                expected_versions: None,
+            }))
+            .unwrap();
+        assert!(second_id > first_id);
+        assert_eq!(await_completion(&async_updater, second_id), TaskStatus::Succeeded { updated: 1 });
+
+        std::fs::remove_file(&store_path).unwrap();
+        let _ = std::fs::remove_file(&wal_path);
+    }
+}
@@ -0,0 +1,25 @@
+use std::sync::{Arc, RwLock};
+
+use segment::entry::entry_point::SegmentEntry;
+use segment::simple_segment::SimpleSegment;
+
+use crate::segment_manager::segment_holder::{LockedSegment, SegmentHolder};
+use crate::segment_manager::segment_managers::SimpleSegmentSearcher;
+
+/// A holder with a handful of pre-seeded points, shared by the segment_manager tests.
+pub fn build_test_holder() -> Arc<RwLock<SegmentHolder>> {
+    let mut segment = SimpleSegment::new();
+    for point_id in 1..=5 {
+        segment
+            .upsert_point(point_id, point_id, &[point_id as f64, point_id as f64, point_id as f64, point_id as f64])
+            .unwrap();
+    }
+
+    let mut holder = SegmentHolder::new();
+    holder.add_segment(LockedSegment::new(segment));
+    Arc::new(RwLock::new(holder))
+}
+
+pub fn build_searcher() -> SimpleSegmentSearcher {
+    SimpleSegmentSearcher::new(build_test_holder())
+}
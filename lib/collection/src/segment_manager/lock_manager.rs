@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+
+use crate::segment_manager::segment_holder::SegmentId;
+
+#[derive(Default)]
+struct LockState {
+    /// Number of readers/writers currently holding a shared lock on this segment.
+    readers: usize,
+    /// Whether a structural change (e.g. a merge swap) currently holds this segment
+    /// exclusively.
+    writer: bool,
+}
+
+/// Tracks per-segment read/write state so operations touching disjoint segments can
+/// proceed in parallel, while merges and other structural changes can still take a
+/// segment exclusively. This sits above the plain `RwLock` each `LockedSegment` already
+/// has for its own data -- that lock guards the segment's contents, this one arbitrates
+/// *which operation is allowed to touch the segment at all* right now.
+pub struct LockManager {
+    state: Mutex<HashMap<SegmentId, LockState>>,
+    condvar: Condvar,
+}
+
+/// Releases every segment lock it was granted for, in one shot, when dropped.
+pub struct SegmentLockGuard<'a> {
+    manager: &'a LockManager,
+    read_ids: Vec<SegmentId>,
+    write_ids: Vec<SegmentId>,
+}
+
+impl<'a> Drop for SegmentLockGuard<'a> {
+    fn drop(&mut self) {
+        let mut state = self.manager.state.lock().unwrap();
+        for id in &self.read_ids {
+            if let Some(lock) = state.get_mut(id) {
+                lock.readers -= 1;
+            }
+        }
+        for id in &self.write_ids {
+            if let Some(lock) = state.get_mut(id) {
+                lock.writer = false;
+            }
+        }
+        drop(state);
+        self.manager.condvar.notify_all();
+    }
+}
+
+impl LockManager {
+    pub fn new() -> Self {
+        LockManager { state: Mutex::new(HashMap::new()), condvar: Condvar::new() }
+    }
+
+    /// Acquires shared access to each of `segment_ids`, for operations that mutate a
+    /// segment's own contents (upserts, deletes, payload edits) but don't change the
+    /// set of segments itself. Blocks while any of them is held exclusively by a
+    /// structural change. Ids are sorted before locking so two callers requesting an
+    /// overlapping set never deadlock against each other.
+    pub fn acquire_read(&self, segment_ids: &[SegmentId]) -> SegmentLockGuard<'_> {
+        let mut sorted = segment_ids.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let mut state = self.state.lock().unwrap();
+        for id in &sorted {
+            loop {
+                let writer_held = state.entry(*id).or_default().writer;
+                if !writer_held {
+                    state.get_mut(id).unwrap().readers += 1;
+                    break;
+                }
+                state = self.condvar.wait(state).unwrap();
+            }
+        }
+        SegmentLockGuard { manager: self, read_ids: sorted, write_ids: Vec::new() }
+    }
+
+    /// Acquires exclusive access to each of `segment_ids`, for structural changes (a
+    /// merge removing/inserting segments) that must not race with an in-flight read or
+    /// write on the same segments. Ids are sorted before locking to avoid deadlock.
+    pub fn acquire_write(&self, segment_ids: &[SegmentId]) -> SegmentLockGuard<'_> {
+        let mut sorted = segment_ids.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let mut state = self.state.lock().unwrap();
+        for id in &sorted {
+            loop {
+                let lock = state.entry(*id).or_default();
+                if !lock.writer && lock.readers == 0 {
+                    lock.writer = true;
+                    break;
+                }
+                state = self.condvar.wait(state).unwrap();
+            }
+        }
+        SegmentLockGuard { manager: self, read_ids: Vec::new(), write_ids: sorted }
+    }
+}
+
+impl Default for LockManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_read_locks_on_disjoint_segments_do_not_block() {
+        let manager = LockManager::new();
+        let _a = manager.acquire_read(&[1]);
+        // A read lock on a different segment must be granted immediately, even while
+        // segment 1's read lock is still held.
+        let (tx, rx) = mpsc::channel();
+        let _b = manager.acquire_read(&[2]);
+        tx.send(()).unwrap();
+        rx.recv_timeout(Duration::from_millis(100)).unwrap();
+    }
+
+    #[test]
+    fn test_write_lock_waits_for_readers_to_release() {
+        let manager = Arc::new(LockManager::new());
+        let read_guard = manager.acquire_read(&[1]);
+
+        let waiter = manager.clone();
+        let handle = thread::spawn(move || {
+            let _write_guard = waiter.acquire_write(&[1]);
+        });
+
+        // The writer can't be granted the lock while the reader still holds it.
+        thread::sleep(Duration::from_millis(50));
+        assert!(!handle.is_finished());
+
+        drop(read_guard);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_write_lock_blocks_subsequent_reads() {
+        let manager = Arc::new(LockManager::new());
+        let write_guard = manager.acquire_write(&[1]);
+
+        let waiter = manager.clone();
+        let handle = thread::spawn(move || {
+            let _read_guard = waiter.acquire_read(&[1]);
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!handle.is_finished());
+
+        drop(write_guard);
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use segment::types::SeqNumberType;
+
+use crate::collection::OperationResult;
+use crate::segment_manager::lock_manager::LockManager;
+use crate::segment_manager::segment_holder::{LockedSegment, SegmentHolder, SegmentId};
+
+/// Thresholds that decide when same-tier segments are considered worth merging.
+#[derive(Debug, Clone)]
+pub struct MergeThresholds {
+    /// Segments with fewer points than this fall into the smallest size tier.
+    pub base_tier_points: usize,
+    /// Number of same-tier segments that must accumulate before they are merged.
+    pub min_segments_to_merge: usize,
+}
+
+impl Default for MergeThresholds {
+    fn default() -> Self {
+        MergeThresholds {
+            base_tier_points: 10_000,
+            min_segments_to_merge: 4,
+        }
+    }
+}
+
+/// A set of segments that a [`MergePolicy`] decided should be consolidated into one.
+pub struct MergeCandidate {
+    pub segment_ids: Vec<SegmentId>,
+}
+
+/// Inspects the current state of a [`SegmentHolder`] and decides which segments, if any,
+/// should be merged together.
+pub trait MergePolicy: Send + Sync {
+    fn candidates(&self, segments: &SegmentHolder) -> Vec<MergeCandidate>;
+}
+
+/// Groups segments into point-count buckets and proposes a merge once a bucket has
+/// accumulated enough same-tier segments.
+pub struct SizeTieredMergePolicy {
+    thresholds: MergeThresholds,
+}
+
+impl SizeTieredMergePolicy {
+    pub fn new(thresholds: MergeThresholds) -> Self {
+        SizeTieredMergePolicy { thresholds }
+    }
+
+    fn tier_of(&self, points_count: usize) -> usize {
+        let mut tier = 0;
+        let mut bound = self.thresholds.base_tier_points;
+        while points_count > bound {
+            tier += 1;
+            bound *= 4;
+        }
+        tier
+    }
+}
+
+impl MergePolicy for SizeTieredMergePolicy {
+    fn candidates(&self, segments: &SegmentHolder) -> Vec<MergeCandidate> {
+        let mut by_tier: HashMap<usize, Vec<SegmentId>> = HashMap::new();
+
+        for (segment_id, locked_segment) in segments.iter() {
+            let points_count = locked_segment.get().read().unwrap().points_count();
+            by_tier
+                .entry(self.tier_of(points_count))
+                .or_default()
+                .push(*segment_id);
+        }
+
+        by_tier
+            .into_iter()
+            .filter(|(_, ids)| ids.len() >= self.thresholds.min_segments_to_merge)
+            .map(|(_, segment_ids)| MergeCandidate { segment_ids })
+            .collect()
+    }
+}
+
+/// Builds one consolidated segment out of `sources`: live points are copied across and
+/// the vector index is rebuilt from scratch, while tombstoned/deleted ids are dropped.
+/// Each copied point keeps its own individual version rather than being stamped with
+/// the merge's global max -- otherwise every untouched point would look "changed" to a
+/// later `expected_version` check. Returns the merged segment together with the max
+/// `op_num` seen among the sources, so callers can carry seq-number ordering forward.
+fn build_merged_segment(
+    segments: &SegmentHolder,
+    sources: &[SegmentId],
+) -> OperationResult<(LockedSegment, SeqNumberType)> {
+    let locked_sources: Vec<LockedSegment> = sources
+        .iter()
+        .filter_map(|id| segments.get(*id).cloned())
+        .collect();
+
+    let mut max_version: SeqNumberType = 0;
+    let merged = segments.build_empty_segment()?;
+
+    {
+        let mut merged_write = merged.get().write().unwrap();
+        for source in &locked_sources {
+            let source_read = source.get().read().unwrap();
+            max_version = max_version.max(source_read.version());
+
+            for point_id in source_read.iter_points() {
+                let point_version = source_read.point_version(point_id).unwrap_or(max_version);
+                let vector = source_read.vector(point_id)?;
+                merged_write.upsert_point(point_version, point_id, &vector)?;
+                if let Some(payload) = source_read.payload(point_id)?.as_ref() {
+                    for (key, value) in payload {
+                        merged_write.set_payload(point_version, point_id, key, value.clone())?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok((merged, max_version))
+}
+
+/// Runs a single merge pass: finds candidates via `policy`, builds a consolidated
+/// segment for each on a small worker pool, then atomically swaps the sources for the
+/// merged result in `segments`. Returns the number of merges performed.
+fn run_merge_pass(
+    segments: &Arc<RwLock<SegmentHolder>>,
+    policy: &dyn MergePolicy,
+    workers: usize,
+    lock_manager: &LockManager,
+) -> OperationResult<usize> {
+    let candidates = policy.candidates(&segments.read().unwrap());
+    if candidates.is_empty() {
+        return Ok(0);
+    }
+
+    let chunk_size = (candidates.len() + workers - 1) / workers.max(1);
+    let merged_results: Vec<OperationResult<usize>> = thread::scope(|scope| {
+        candidates
+            .chunks(chunk_size.max(1))
+            .map(|chunk| {
+                scope.spawn(move || -> OperationResult<usize> {
+                    let mut merged = 0;
+                    for candidate in chunk {
+                        let read_guard = segments.read().unwrap();
+                        let (mut merged_segment, mut max_op_num) =
+                            build_merged_segment(&read_guard, &candidate.segment_ids)?;
+                        let snapshot_versions: Vec<SeqNumberType> = candidate
+                            .segment_ids
+                            .iter()
+                            .map(|id| {
+                                read_guard
+                                    .get(*id)
+                                    .map(|segment| segment.get().read().unwrap().version())
+                                    .unwrap_or(0)
+                            })
+                            .collect();
+                        drop(read_guard);
+
+                        // Hold the source segments exclusively before swapping. Because
+                        // `acquire_write` only succeeds once every reader has released
+                        // (see `LockManager`), by the time we're granted this lock any
+                        // write that was in flight when we read the sources above has
+                        // already completed, and no new one can start until we release.
+                        let _structural_lock = lock_manager.acquire_write(&candidate.segment_ids);
+
+                        // If a source changed since the snapshot we copied from, the
+                        // optimistic copy above is stale -- rebuild it now. This rebuild
+                        // is guaranteed final: we're holding every source exclusively, so
+                        // nothing else can write to them while we do it.
+                        let guard = segments.read().unwrap();
+                        let changed = candidate.segment_ids.iter().zip(&snapshot_versions).any(|(id, snapshot_version)| {
+                            guard
+                                .get(*id)
+                                .map(|segment| segment.get().read().unwrap().version())
+                                .unwrap_or(0)
+                                != *snapshot_version
+                        });
+                        // A losing racer against another merge pass over the same (or
+                        // overlapping) candidate sees every source already gone here --
+                        // rebuilding would silently produce an empty segment and
+                        // `swap_segments` would still insert it, leaking a permanent
+                        // empty segment into the holder. Skip the swap outright instead.
+                        if changed && candidate.segment_ids.iter().all(|id| guard.get(*id).is_none()) {
+                            drop(guard);
+                            continue;
+                        }
+                        if changed {
+                            let (rebuilt_segment, rebuilt_max_op_num) = build_merged_segment(&guard, &candidate.segment_ids)?;
+                            merged_segment = rebuilt_segment;
+                            max_op_num = rebuilt_max_op_num;
+                        }
+                        drop(guard);
+
+                        let mut write_guard = segments.write().unwrap();
+                        write_guard.swap_segments(
+                            &candidate.segment_ids,
+                            merged_segment,
+                            max_op_num,
+                        )?;
+                        merged += 1;
+                    }
+                    Ok(merged)
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut merged_count = 0;
+    for result in merged_results {
+        merged_count += result?;
+    }
+    Ok(merged_count)
+}
+
+/// Background optimizer that periodically merges small segments together according to
+/// a pluggable [`MergePolicy`], so search latency and deleted-point reclamation don't
+/// degrade as a collection accumulates segments from ongoing writes.
+pub struct MergeOptimizer {
+    segments: Arc<RwLock<SegmentHolder>>,
+    policy: Box<dyn MergePolicy>,
+    workers: usize,
+    lock_manager: Arc<LockManager>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Handle to a running [`MergeOptimizer`] background loop.
+pub struct MergeOptimizerHandle {
+    stop: Arc<AtomicBool>,
+    join_handle: JoinHandle<()>,
+}
+
+impl MergeOptimizerHandle {
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.join_handle.join();
+    }
+}
+
+impl MergeOptimizer {
+    pub fn new(
+        segments: Arc<RwLock<SegmentHolder>>,
+        policy: Box<dyn MergePolicy>,
+        workers: usize,
+        lock_manager: Arc<LockManager>,
+    ) -> Self {
+        MergeOptimizer {
+            segments,
+            policy,
+            workers: workers.max(1),
+            lock_manager,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Forces a full merge pass right now, blocking until it completes.
+    pub fn optimize(&self) -> OperationResult<usize> {
+        run_merge_pass(&self.segments, self.policy.as_ref(), self.workers, self.lock_manager.as_ref())
+    }
+
+    /// Spawns the background loop that periodically looks for merge candidates. Sleeps
+    /// in short increments rather than one long `sleep(check_interval)` so `stop()`
+    /// doesn't have to wait out the whole interval to take effect.
+    pub fn spawn(self, check_interval: Duration) -> MergeOptimizerHandle {
+        const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+        let stop = self.stop.clone();
+        let join_handle = thread::spawn(move || {
+            while !self.stop.load(Ordering::Relaxed) {
+                if let Err(error) = self.optimize() {
+                    log::error!("segment merge pass failed: {:?}", error);
+                }
+
+                let mut waited = Duration::ZERO;
+                while waited < check_interval && !self.stop.load(Ordering::Relaxed) {
+                    let step = STOP_POLL_INTERVAL.min(check_interval - waited);
+                    thread::sleep(step);
+                    waited += step;
+                }
+            }
+        });
+        MergeOptimizerHandle { stop, join_handle }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use segment::entry::entry_point::SegmentEntry;
+    use segment::simple_segment::SimpleSegment;
+
+    #[test]
+    fn test_build_merged_segment_keeps_individual_point_versions() {
+        let segments = Arc::new(RwLock::new(SegmentHolder::new()));
+
+        let mut first = SimpleSegment::new();
+        first.upsert_point(1, 1, &[1.0, 1.0]).unwrap();
+        let mut second = SimpleSegment::new();
+        second.upsert_point(10, 2, &[2.0, 2.0]).unwrap();
+
+        let ids = {
+            let mut guard = segments.write().unwrap();
+            vec![
+                guard.add_segment(LockedSegment::new(first)),
+                guard.add_segment(LockedSegment::new(second)),
+            ]
+        };
+
+        let guard = segments.read().unwrap();
+        let (merged, max_op_num) = build_merged_segment(&guard, &ids).unwrap();
+        assert_eq!(max_op_num, 10);
+
+        let merged_read = merged.get().read().unwrap();
+        // Point 1 keeps its own version (1), not the merge-wide max (10) -- otherwise a
+        // client holding `expected_version: 1` for it would see a spurious conflict
+        // after a merge that never touched it.
+        assert_eq!(merged_read.point_version(1), Some(1));
+        assert_eq!(merged_read.point_version(2), Some(10));
+    }
+
+    #[test]
+    fn test_merge_carries_forward_max_version_from_tombstoned_point() {
+        let segments = Arc::new(RwLock::new(SegmentHolder::new()));
+        let lock_manager = segments.read().unwrap().lock_manager();
+
+        let mut first = SimpleSegment::new();
+        first.upsert_point(1, 1, &[1.0, 1.0]).unwrap();
+        // Deleting point 1 at op_num 50 bumps the segment's own version to 50, even
+        // though point 1 never makes it into the merged segment.
+        first.delete_point(50, 1).unwrap();
+        let mut second = SimpleSegment::new();
+        second.upsert_point(2, 2, &[2.0, 2.0]).unwrap();
+
+        let ids = {
+            let mut guard = segments.write().unwrap();
+            vec![
+                guard.add_segment(LockedSegment::new(first)),
+                guard.add_segment(LockedSegment::new(second)),
+            ]
+        };
+
+        let policy = SizeTieredMergePolicy::new(MergeThresholds {
+            base_tier_points: 1_000,
+            min_segments_to_merge: ids.len(),
+        });
+
+        let merged_count = run_merge_pass(&segments, &policy, 1, &lock_manager).unwrap();
+        assert_eq!(merged_count, 1);
+
+        let guard = segments.read().unwrap();
+        let (_, merged) = guard.iter().next().unwrap();
+        // The merged segment has no record of point 1 at all, yet its version must
+        // still reflect the 50 that the tombstoning op bumped the source to --
+        // otherwise `flush()`/`min_flushed_op_num()` would under-report what's durable.
+        assert_eq!(merged.get().read().unwrap().version(), 50);
+    }
+
+    #[test]
+    fn test_merge_does_not_lose_concurrent_write() {
+        let segments = Arc::new(RwLock::new(SegmentHolder::new()));
+        let lock_manager = segments.read().unwrap().lock_manager();
+
+        let mut segment_ids = Vec::new();
+        for i in 0..3 {
+            let mut segment = SimpleSegment::new();
+            segment.upsert_point(1, 100 + i, &[1.0, 1.0]).unwrap();
+            segment_ids.push(segments.write().unwrap().add_segment(LockedSegment::new(segment)));
+        }
+        let target_id = segment_ids[0];
+        let target_segment = segments.read().unwrap().get(target_id).unwrap().clone();
+
+        let policy = SizeTieredMergePolicy::new(MergeThresholds {
+            base_tier_points: 1_000,
+            min_segments_to_merge: segment_ids.len(),
+        });
+
+        // Simulates a write landing in a source segment mid-merge: takes the same
+        // per-segment lock `apply_points` would, so it races `run_merge_pass`'s
+        // `acquire_write` exactly the way production traffic would.
+        let writer_lock_manager = lock_manager.clone();
+        let writer = thread::spawn(move || {
+            let _guard = writer_lock_manager.acquire_read(&[target_id]);
+            thread::sleep(Duration::from_millis(20));
+            target_segment.get().write().unwrap().upsert_point(2, 999, &[2.0, 2.0]).unwrap();
+        });
+
+        thread::sleep(Duration::from_millis(5));
+        let merged_count = run_merge_pass(&segments, &policy, 2, &lock_manager).unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(merged_count, 1);
+        let guard = segments.read().unwrap();
+        assert_eq!(guard.iter().count(), 1);
+        let (_, merged) = guard.iter().next().unwrap();
+        assert_eq!(merged.get().read().unwrap().point_version(999), Some(2));
+    }
+
+    #[test]
+    fn test_apply_points_does_not_deadlock_against_a_concurrent_merge_swap() {
+        let segments = Arc::new(RwLock::new(SegmentHolder::new()));
+        let lock_manager = segments.read().unwrap().lock_manager();
+
+        let mut segment = SimpleSegment::new();
+        segment.upsert_point(1, 100, &[1.0, 1.0]).unwrap();
+        let segment_id = segments.write().unwrap().add_segment(LockedSegment::new(segment));
+
+        // Simulates `run_merge_pass` having already taken the structural lock on its
+        // source segment, before it goes back for `segments`' own write lock to swap.
+        let structural_lock = lock_manager.acquire_write(&[segment_id]);
+
+        let (writer_tx, writer_rx) = mpsc::channel();
+        let writer_segments = segments.clone();
+        let writer = thread::spawn(move || {
+            let result = SegmentHolder::apply_points(&writer_segments, 2, &[100], |id, seg| seg.delete_point(2, id));
+            let _ = writer_tx.send(result);
+        });
+
+        // Give the writer time to reach (and block inside) `lock_manager.acquire_read`.
+        thread::sleep(Duration::from_millis(50));
+
+        // Simulates the merge's own subsequent step: with `structural_lock` still
+        // held, it wants `segments`' write lock to perform the swap. Under the bug
+        // this regression test targets, the writer above held `segments`' read guard
+        // for its *entire* call -- including while blocked on `lock_manager` -- so
+        // this write lock could never be granted, and the writer could never get its
+        // `lock_manager` read lock because this thread holds it: a genuine AB-BA
+        // deadlock. It must be possible to grant this write lock while the writer is
+        // still queued on `lock_manager`.
+        let (swap_tx, swap_rx) = mpsc::channel();
+        let swap_segments = segments.clone();
+        let swapper = thread::spawn(move || {
+            let _write_guard = swap_segments.write().unwrap();
+            let _ = swap_tx.send(());
+        });
+        swap_rx
+            .recv_timeout(Duration::from_secs(2))
+            .expect("segments' write lock deadlocked against a writer queued on the structural lock");
+        swapper.join().unwrap();
+
+        drop(structural_lock);
+        writer_rx
+            .recv_timeout(Duration::from_secs(2))
+            .expect("apply_points never resumed after the structural lock was released")
+            .unwrap();
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_losing_merge_race_does_not_leak_empty_segment() {
+        let segments = Arc::new(RwLock::new(SegmentHolder::new()));
+        let lock_manager = segments.read().unwrap().lock_manager();
+
+        let mut segment_ids = Vec::new();
+        for i in 0..4 {
+            let mut segment = SimpleSegment::new();
+            segment.upsert_point(1, i, &[1.0, 1.0]).unwrap();
+            segment_ids.push(segments.write().unwrap().add_segment(LockedSegment::new(segment)));
+        }
+
+        let policy = SizeTieredMergePolicy::new(MergeThresholds {
+            base_tier_points: 1_000,
+            min_segments_to_merge: segment_ids.len(),
+        });
+
+        // Pre-acquire the structural lock the loser's `run_merge_pass` will need, so it
+        // blocks right before its swap -- simulating another merge pass about to win
+        // the race over these same source segments.
+        let structural_lock = lock_manager.acquire_write(&segment_ids);
+
+        let (tx, rx) = mpsc::channel();
+        let loser_segments = segments.clone();
+        let loser_lock_manager = lock_manager.clone();
+        let loser = thread::spawn(move || {
+            let result = run_merge_pass(&loser_segments, &policy, 1, &loser_lock_manager);
+            let _ = tx.send(result);
+        });
+
+        // Give the loser time to build its optimistic copy and queue on the structural lock.
+        thread::sleep(Duration::from_millis(50));
+
+        // Simulate the winner: swap the exact same sources away for a real merged
+        // segment while the loser is still queued on the structural lock above.
+        let (winner_segment, winner_max_op_num) = {
+            let guard = segments.read().unwrap();
+            build_merged_segment(&guard, &segment_ids).unwrap()
+        };
+        segments.write().unwrap().swap_segments(&segment_ids, winner_segment, winner_max_op_num).unwrap();
+
+        drop(structural_lock);
+
+        let merged_count = rx
+            .recv_timeout(Duration::from_secs(2))
+            .expect("loser's run_merge_pass never resumed after the structural lock was released")
+            .unwrap();
+        loser.join().unwrap();
+
+        // The loser must see every one of its sources already gone and skip its swap
+        // rather than inserting a second, empty segment alongside the winner's real one.
+        assert_eq!(merged_count, 0);
+        let guard = segments.read().unwrap();
+        assert_eq!(guard.iter().count(), 1);
+        let (_, only_segment) = guard.iter().next().unwrap();
+        assert_eq!(only_segment.get().read().unwrap().points_count(), 4);
+    }
+}
@@ -0,0 +1,11 @@
+pub mod async_updater;
+pub mod lock_manager;
+pub mod merge_optimizer;
+pub mod segment_holder;
+pub mod segment_managers;
+pub mod simple_segment_updater;
+pub mod task_store;
+pub mod wal;
+
+#[cfg(test)]
+pub mod fixtures;
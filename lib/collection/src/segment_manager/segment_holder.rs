@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use rand::seq::IteratorRandom;
+
+use segment::entry::entry_point::SegmentEntry;
+use segment::types::{PointIdType, SeqNumberType};
+
+use crate::collection::OperationResult;
+use crate::segment_manager::lock_manager::LockManager;
+
+pub type SegmentId = u64;
+
+/// Shared handle to a segment: its own `RwLock` guards the segment's contents, and is
+/// independent of the `LockManager`'s per-segment locks, which arbitrate whether an
+/// operation is allowed to touch the segment at all right now (see `lock_manager`).
+#[derive(Clone)]
+pub struct LockedSegment(Arc<RwLock<Box<dyn SegmentEntry>>>);
+
+impl LockedSegment {
+    pub fn new(segment: impl SegmentEntry + 'static) -> Self {
+        LockedSegment(Arc::new(RwLock::new(Box::new(segment))))
+    }
+
+    pub fn get(&self) -> &RwLock<Box<dyn SegmentEntry>> {
+        &self.0
+    }
+}
+
+/// Owns the set of segments that make up a collection's storage. Per-segment read/write
+/// admission is arbitrated by a shared `LockManager` so operations touching disjoint
+/// segments can run in parallel while structural changes (merges) can still take a
+/// segment exclusively.
+pub struct SegmentHolder {
+    segments: HashMap<SegmentId, LockedSegment>,
+    lock_manager: Arc<LockManager>,
+    next_id: SegmentId,
+}
+
+impl SegmentHolder {
+    pub fn new() -> Self {
+        SegmentHolder { segments: HashMap::new(), lock_manager: Arc::new(LockManager::new()), next_id: 0 }
+    }
+
+    pub fn lock_manager(&self) -> Arc<LockManager> {
+        self.lock_manager.clone()
+    }
+
+    pub fn add_segment(&mut self, segment: LockedSegment) -> SegmentId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.segments.insert(id, segment);
+        id
+    }
+
+    pub fn get(&self, segment_id: SegmentId) -> Option<&LockedSegment> {
+        self.segments.get(&segment_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&SegmentId, &LockedSegment)> {
+        self.segments.iter()
+    }
+
+    pub fn random_segment(&self) -> Option<LockedSegment> {
+        self.segments.values().choose(&mut rand::thread_rng()).cloned()
+    }
+
+    pub fn random_segment_id(&self) -> Option<(SegmentId, LockedSegment)> {
+        self.segments.iter().choose(&mut rand::thread_rng()).map(|(id, segment)| (*id, segment.clone()))
+    }
+
+    /// Finds whichever segment currently holds `point_id`, without taking any lock
+    /// beyond each segment's own brief read to check for the id.
+    fn find_owner(&self, point_id: PointIdType) -> Option<(SegmentId, LockedSegment)> {
+        self.segments.iter().find_map(|(id, segment)| {
+            segment.get().read().unwrap().point_version(point_id).is_some().then(|| (*id, segment.clone()))
+        })
+    }
+
+    /// Applies `f` to whichever segment currently holds `point_id`, for every id in
+    /// `ids`, taking only the per-segment lock this call actually needs: a cheap,
+    /// unlocked probe to find the owning segment, then a shared `LockManager` lock on
+    /// just that one segment for the mutation. If a merge swaps the owning segment out
+    /// from under us between the probe and the lock being granted, the probe is
+    /// retried. Concurrent calls touching a disjoint set of segments proceed in
+    /// parallel; a merge holding an exclusive lock on one of these segments blocks only
+    /// the ids that live there (and vice versa).
+    ///
+    /// Takes `segments` by the shared `Arc<RwLock<_>>` rather than as `&self`, and only
+    /// ever holds its read guard briefly, for the probe/re-check -- never across the
+    /// `lock_manager.acquire_read` call. `run_merge_pass` takes `lock_manager`'s
+    /// exclusive lock on a segment *before* it goes back for `segments`' own write lock
+    /// to swap; a caller that held `segments`' read guard while blocked on
+    /// `lock_manager.acquire_read` for that same segment would deadlock against that
+    /// (merge waits on `segments`' write lock, which can't be granted while our read
+    /// guard is held; we wait on `lock_manager`, which can't be granted while merge
+    /// holds it).
+    pub fn apply_points<F>(
+        segments: &Arc<RwLock<SegmentHolder>>,
+        _op_num: SeqNumberType,
+        ids: &[PointIdType],
+        mut f: F,
+    ) -> OperationResult<usize>
+    where
+        F: FnMut(PointIdType, &mut dyn SegmentEntry) -> segment::entry::entry_point::Result<bool>,
+    {
+        let lock_manager = segments.read().unwrap().lock_manager();
+        let mut applied = 0;
+        for point_id in ids.iter().copied() {
+            loop {
+                let (segment_id, locked_segment) = match segments.read().unwrap().find_owner(point_id) {
+                    Some(owner) => owner,
+                    None => break,
+                };
+
+                let _segment_lock = lock_manager.acquire_read(&[segment_id]);
+
+                // The segment might have been merged away (or the point deleted from
+                // it) while we were waiting for the lock -- re-check and retry rather
+                // than mutating a segment that no longer owns this point.
+                if segments.read().unwrap().get(segment_id).is_none() {
+                    continue;
+                }
+                if locked_segment.get().read().unwrap().point_version(point_id).is_none() {
+                    continue;
+                }
+
+                let mut segment = locked_segment.get().write().unwrap();
+                if f(point_id, &mut **segment)? {
+                    applied += 1;
+                }
+                break;
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Applies `f` to every segment, taking each one's lock in turn. Used for
+    /// segment-wide operations like `wipe_payload` that aren't targeted at specific
+    /// points.
+    ///
+    /// Same `Arc<RwLock<_>>`-and-brief-guard shape as [`Self::apply_points`], and for
+    /// the same reason: the snapshot and the `lock_manager` clone are taken under a
+    /// momentary read guard that's dropped before `lock_manager.acquire_read` blocks.
+    pub fn apply_segments<F>(
+        segments: &Arc<RwLock<SegmentHolder>>,
+        _op_num: SeqNumberType,
+        mut f: F,
+    ) -> OperationResult<usize>
+    where
+        F: FnMut(&mut dyn SegmentEntry) -> segment::entry::entry_point::Result<usize>,
+    {
+        let (lock_manager, snapshot) = {
+            let guard = segments.read().unwrap();
+            let mut snapshot: Vec<(SegmentId, LockedSegment)> =
+                guard.segments.iter().map(|(id, segment)| (*id, segment.clone())).collect();
+            snapshot.sort_unstable_by_key(|(id, _)| *id);
+            (guard.lock_manager.clone(), snapshot)
+        };
+
+        let segment_ids: Vec<SegmentId> = snapshot.iter().map(|(id, _)| *id).collect();
+        let _segments_lock = lock_manager.acquire_read(&segment_ids);
+
+        let mut total = 0;
+        for (_, locked_segment) in &snapshot {
+            let mut segment = locked_segment.get().write().unwrap();
+            total += f(&mut **segment)?;
+        }
+        Ok(total)
+    }
+
+    /// Builds a fresh, empty segment of the same kind this holder stores -- the
+    /// starting point for a merge's consolidated output.
+    pub fn build_empty_segment(&self) -> OperationResult<LockedSegment> {
+        Ok(LockedSegment::new(segment::simple_segment::SimpleSegment::new()))
+    }
+
+    /// Atomically removes `source_ids` and inserts `new_segment` in their place. Caller
+    /// is expected to already hold an exclusive `LockManager` lock on `source_ids` so no
+    /// write can land in one of them between this holder's structural change and that
+    /// lock being taken. `max_op_num` is the true max op_num among the sources' contents
+    /// (see `build_merged_segment`) and is stamped onto `new_segment` so its `version()`
+    /// doesn't under-report what's actually durable in it.
+    pub fn swap_segments(&mut self, source_ids: &[SegmentId], new_segment: LockedSegment, max_op_num: SeqNumberType) -> OperationResult<SegmentId> {
+        new_segment.get().write().unwrap().bump_version(max_op_num);
+        for id in source_ids {
+            self.segments.remove(id);
+        }
+        Ok(self.add_segment(new_segment))
+    }
+}
+
+impl Default for SegmentHolder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::{Arc, RwLock};
+
+use segment::types::{PayloadKeyType, PayloadType, PointIdType, SeqNumberType, TheMap, VectorElementType};
+
+use crate::collection::OperationResult;
+use crate::operations::CollectionUpdateOperations;
+use crate::segment_manager::segment_holder::SegmentHolder;
+
+/// Applies update operations to a collection's segments.
+pub trait SegmentUpdater {
+    /// Applies `operation`, tagged with `op_num`, to whichever segments it targets.
+    /// Returns the number of points the operation actually touched.
+    fn update(&self, op_num: SeqNumberType, operation: &CollectionUpdateOperations) -> OperationResult<usize>;
+}
+
+/// A single point's data as returned by a search/retrieve call.
+pub struct Record {
+    pub id: PointIdType,
+    /// The point's current version, so a caller can round-trip it back as an
+    /// `expected_version` on a later conditional update.
+    pub version: SeqNumberType,
+    pub vector: Option<Vec<VectorElementType>>,
+    pub payload: Option<TheMap<PayloadKeyType, PayloadType>>,
+}
+
+/// Reads point data back out of a collection's segments.
+pub trait SegmentSearcher {
+    fn retrieve(&self, ids: &[PointIdType], with_payload: bool, with_vector: bool) -> Vec<Record>;
+}
+
+/// Looks each requested point up across all segments, returning data for whichever ones
+/// are found.
+pub struct SimpleSegmentSearcher {
+    pub segments: Arc<RwLock<SegmentHolder>>,
+}
+
+impl SimpleSegmentSearcher {
+    pub fn new(segments: Arc<RwLock<SegmentHolder>>) -> Self {
+        SimpleSegmentSearcher { segments }
+    }
+}
+
+impl SegmentSearcher for SimpleSegmentSearcher {
+    fn retrieve(&self, ids: &[PointIdType], with_payload: bool, with_vector: bool) -> Vec<Record> {
+        let segments = self.segments.read().unwrap();
+        ids.iter()
+            .filter_map(|id| {
+                segments.iter().find_map(|(_, locked_segment)| {
+                    let segment = locked_segment.get().read().unwrap();
+                    let version = segment.point_version(*id)?;
+                    Some(Record {
+                        id: *id,
+                        version,
+                        vector: if with_vector { segment.vector(*id).ok() } else { None },
+                        payload: if with_payload { segment.payload(*id).ok().flatten() } else { None },
+                    })
+                })
+            })
+            .collect()
+    }
+}
@@ -1,28 +1,145 @@
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
-use crate::segment_manager::segment_holder::{SegmentHolder, LockedSegment};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use crate::segment_manager::segment_holder::SegmentHolder;
 use crate::segment_manager::segment_managers::SegmentUpdater;
+use crate::segment_manager::lock_manager::LockManager;
+use crate::segment_manager::merge_optimizer::{MergeOptimizer, MergeOptimizerHandle, MergePolicy, MergeThresholds, SizeTieredMergePolicy};
+use crate::segment_manager::wal::Wal;
 use crate::operations::CollectionUpdateOperations;
 use crate::collection::{OperationResult, UpdateError};
 use segment::types::{SeqNumberType, PointIdType, PayloadKeyType};
-use segment::entry::entry_point::{OperationError, SegmentEntry, Result};
+use segment::entry::entry_point::{OperationError, Result};
 use std::collections::{HashSet, HashMap};
 use crate::operations::types::VectorType;
-use rand::Rng;
 use crate::operations::point_ops::PointOps;
-use crate::operations::payload_ops::{PayloadOps, PayloadInterface, PayloadVariant};
+use crate::operations::payload_ops::{PayloadOps, PayloadInterface};
 
-struct SimpleSegmentUpdater {
+/// How often the background `MergeOptimizer` checks for merge candidates.
+const MERGE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct SimpleSegmentUpdater {
     segments: Arc<RwLock<SegmentHolder>>,
+    lock_manager: Arc<LockManager>,
+    optimizer: MergeOptimizer,
+    wal: Wal,
+    merge_handle: Option<MergeOptimizerHandle>,
+    /// Serializes "does this point id already exist anywhere?" decisions for
+    /// `upsert_points`'s new-point path. A per-segment `LockManager` lock only excludes
+    /// writers on the one segment a new id happens to land in, not other concurrent
+    /// callers deciding the *same* id is new and picking a different segment -- this
+    /// mutex closes that window by making the decide-then-insert step indivisible
+    /// process-wide.
+    new_point_lock: Mutex<()>,
 }
 
 
 impl SimpleSegmentUpdater {
-    fn check_unprocessed_points(points: &Vec<PointIdType>, processed: &HashSet<PointIdType>) -> OperationResult<usize> {
+    pub fn new(
+        segments: Arc<RwLock<SegmentHolder>>,
+        merge_thresholds: MergeThresholds,
+        merge_workers: usize,
+        wal_path: impl AsRef<Path>,
+    ) -> OperationResult<Self> {
+        let lock_manager = segments.read().unwrap().lock_manager();
+
+        let policy: Box<dyn MergePolicy> = Box::new(SizeTieredMergePolicy::new(merge_thresholds.clone()));
+        let optimizer = MergeOptimizer::new(segments.clone(), policy, merge_workers, lock_manager.clone());
+
+        // A second optimizer instance drives the background loop so `optimize()` stays
+        // available for callers that want to force an immediate pass.
+        let background_policy: Box<dyn MergePolicy> = Box::new(SizeTieredMergePolicy::new(merge_thresholds));
+        let background_optimizer = MergeOptimizer::new(segments.clone(), background_policy, merge_workers, lock_manager.clone());
+
+        let updater = SimpleSegmentUpdater {
+            optimizer,
+            wal: Wal::open(wal_path)?,
+            segments,
+            lock_manager,
+            merge_handle: Some(background_optimizer.spawn(MERGE_CHECK_INTERVAL)),
+            new_point_lock: Mutex::new(()),
+        };
+        updater.recover()?;
+        Ok(updater)
+    }
+
+    /// Forces a full segment merge right now, blocking until it completes.
+    pub fn optimize(&self) -> OperationResult<usize> {
+        self.optimizer.optimize()
+    }
+
+    /// Replays every WAL entry whose `op_num` is beyond what's already durable in
+    /// segments. Because segment methods compare seq numbers before applying an op,
+    /// re-applying an entry that's already reflected in a segment is a no-op, so this
+    /// is safe to run unconditionally on startup. `update()` logs an operation to the
+    /// WAL before applying it, so an op that got rejected the first time it ran is
+    /// durably logged anyway. `VersionConflict` and `NotFound` are both deterministic
+    /// rejections -- replaying them reproduces the same rejection, not a new failure --
+    /// so they're skipped rather than treated as a recovery failure. A `ServiceError`
+    /// reflects actual infrastructure trouble (e.g. no segments at all) and still
+    /// aborts recovery.
+    pub fn recover(&self) -> OperationResult<usize> {
+        let min_flushed_op_num = self.min_flushed_op_num()?;
+        let mut replayed = 0;
+        for (op_num, operation) in self.wal.read_all()? {
+            if op_num > min_flushed_op_num {
+                match self.apply(op_num, &operation) {
+                    Ok(_) => replayed += 1,
+                    Err(UpdateError::VersionConflict { .. }) => continue,
+                    Err(UpdateError::NotFound { .. }) => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(replayed)
+    }
+
+    /// Truncates the WAL down to entries still needed for recovery, i.e. those beyond
+    /// the lowest seq number flushed across all segments.
+    pub fn checkpoint(&self) -> OperationResult<()> {
+        let min_flushed_op_num = self.min_flushed_op_num()?;
+        self.wal.checkpoint(min_flushed_op_num)
+    }
+
+    fn min_flushed_op_num(&self) -> OperationResult<SeqNumberType> {
+        let segments = self.segments.read().unwrap();
+        let min = segments
+            .iter()
+            .map(|(_, locked_segment)| locked_segment.get().read().unwrap().flush())
+            .try_fold(SeqNumberType::MAX, |acc, flushed| flushed.map(|v| acc.min(v)))?;
+        Ok(min)
+    }
+
+    /// Applies an already-durable operation to the segments, without touching the WAL.
+    /// Used both by `update()` after logging and by `recover()` during WAL replay.
+    fn apply(&self, op_num: SeqNumberType, operation: &CollectionUpdateOperations) -> OperationResult<usize> {
+        match operation {
+            CollectionUpdateOperations::PointOperation(point_operation) => self.process_point_operation(op_num, point_operation),
+            CollectionUpdateOperations::PayloadOperation(payload_operation) => self.process_payload_operation(op_num, payload_operation),
+        }
+    }
+
+    /// Rejects a mutation to `point_id` with `VersionConflict` if `expected_versions`
+    /// carries an entry for it that doesn't match `actual`. Callable uniformly from
+    /// inside an `apply_points` closure (where `actual` is the version read under that
+    /// segment's lock) and from a direct new-point insertion (where `actual` is `0`,
+    /// the "never written" sentinel, since the point doesn't exist anywhere yet).
+    fn check_expected_version(
+        point_id: PointIdType,
+        expected_versions: &Option<HashMap<PointIdType, SeqNumberType>>,
+        actual: SeqNumberType,
+    ) -> Result<()> {
+        match expected_versions.as_ref().and_then(|versions| versions.get(&point_id).copied()) {
+            Some(expected) if expected != actual => Err(OperationError::VersionConflict { point_id, expected, actual }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_unprocessed_points(points: &[PointIdType], processed: &HashSet<PointIdType>) -> OperationResult<usize> {
         let missed_point = points
             .iter()
             .cloned()
-            .filter(|p| !processed.contains(p))
-            .next();
+            .find(|p| !processed.contains(p));
         match missed_point {
             None => Ok(processed.len()),
             Some(missed_point) => Err(UpdateError::NotFound { missed_point_id: missed_point }),
@@ -30,55 +147,98 @@ impl SimpleSegmentUpdater {
     }
 
     /// Tries to delete points from all segments, returns number of actually deleted points
-    fn delete_points(&self, op_num: SeqNumberType, ids: &Vec<PointIdType>) -> OperationResult<usize> {
-        self.segments.read().unwrap()
-            .apply_points(op_num, ids, |id, write_segment|
-                write_segment.delete_point(op_num, id),
-            )
+    fn delete_points(&self, op_num: SeqNumberType, ids: &[PointIdType]) -> OperationResult<usize> {
+        SegmentHolder::apply_points(&self.segments, op_num, ids, |id, write_segment|
+            write_segment.delete_point(op_num, id),
+        )
     }
 
 
     /// Checks point id in each segment, update point if found.
     /// All not found points are inserted into random segment.
     /// Returns: number of updated points.
-    fn upsert_points(&self, op_num: SeqNumberType, ids: &Vec<PointIdType>, vectors: &Vec<VectorType>) -> OperationResult<usize> {
+    fn upsert_points(
+        &self,
+        op_num: SeqNumberType,
+        ids: &[PointIdType],
+        vectors: &[VectorType],
+        expected_versions: &Option<HashMap<PointIdType, SeqNumberType>>,
+    ) -> OperationResult<usize> {
         let mut updated_points: HashSet<PointIdType> = Default::default();
         let points_map: HashMap<PointIdType, &VectorType> = ids.iter().cloned().zip(vectors).collect();
 
-        let segments = self.segments.read().unwrap();
-
-        let res = segments.apply_points(op_num, ids, |id, write_segment| {
+        let mut res = SegmentHolder::apply_points(&self.segments, op_num, ids, |id, write_segment| {
+            SimpleSegmentUpdater::check_expected_version(id, expected_versions, write_segment.point_version(id).unwrap_or(0))?;
             updated_points.insert(id);
             write_segment.upsert_point(op_num, id, points_map[&id])
         })?;
 
-        let new_point_ids = ids
+        let new_point_ids: Vec<PointIdType> = ids
             .iter()
             .cloned()
+            .filter(|x| !updated_points.contains(x))
+            .collect();
+
+        if new_point_ids.is_empty() {
+            return Ok(res);
+        }
+
+        // Closes the window between "not found above" and "insert into a segment":
+        // without this, two concurrent calls could each conclude the same brand-new id
+        // is absent and insert it into two different segments. Holding this lock makes
+        // the decide-then-insert step below indivisible across every concurrent caller,
+        // not just the one segment a given caller happens to land in.
+        let _new_point_guard = self.new_point_lock.lock().unwrap();
+
+        // Re-check under the lock: a concurrent caller may have inserted one of these
+        // ids into some segment while we were waiting for it. If so, update it in place
+        // instead of inserting a duplicate.
+        res += SegmentHolder::apply_points(&self.segments, op_num, &new_point_ids, |id, write_segment| {
+            SimpleSegmentUpdater::check_expected_version(id, expected_versions, write_segment.point_version(id).unwrap_or(0))?;
+            updated_points.insert(id);
+            write_segment.upsert_point(op_num, id, points_map[&id])
+        })?;
+
+        let still_new_ids = new_point_ids
+            .into_iter()
             .filter(|x| !updated_points.contains(x));
 
-        let write_segment = segments.random_segment();
-        return match write_segment {
-            None => Err(UpdateError::ServiceError { error: "No segments exists, expected at least one".to_string() }),
-            Some(segment) => {
-                let mut write_segment = segment.write().unwrap();
-                for point_id in new_point_ids {
-                    write_segment.upsert_point(op_num, point_id, points_map[&point_id]);
+        for point_id in still_new_ids {
+            SimpleSegmentUpdater::check_expected_version(point_id, expected_versions, 0)?;
+            loop {
+                let (segment_id, segment) = match self.segments.read().unwrap().random_segment_id() {
+                    Some(target) => target,
+                    None => return Err(UpdateError::ServiceError { error: "No segments exists, expected at least one".to_string() }),
+                };
+
+                let _segment_lock = self.lock_manager.acquire_read(&[segment_id]);
+
+                // The segment might have been merged away while we were waiting for the
+                // lock -- re-check and pick a new target rather than inserting into an
+                // orphaned segment that was already swapped out of the holder.
+                if self.segments.read().unwrap().get(segment_id).is_none() {
+                    continue;
                 }
-                Ok(res)
+
+                segment.get().write().unwrap().upsert_point(op_num, point_id, points_map[&point_id])?;
+                res += 1;
+                break;
             }
-        };
+        }
+        Ok(res)
     }
 
     fn set_payload(
         &self,
         op_num: SeqNumberType,
         payload: &HashMap<PayloadKeyType, PayloadInterface>,
-        points: &Vec<PointIdType>,
+        points: &[PointIdType],
+        expected_versions: &Option<HashMap<PointIdType, SeqNumberType>>,
     ) -> OperationResult<usize> {
         let mut updated_points: HashSet<PointIdType> = Default::default();
 
-        let res = self.segments.read().unwrap().apply_points(op_num, points, |id, write_segment| {
+        let res = SegmentHolder::apply_points(&self.segments, op_num, points, |id, write_segment| {
+            SimpleSegmentUpdater::check_expected_version(id, expected_versions, write_segment.point_version(id).unwrap_or(0))?;
             updated_points.insert(id);
             let mut res = true;
             for (key, payload) in payload {
@@ -94,20 +254,20 @@ impl SimpleSegmentUpdater {
     fn delete_payload(
         &self,
         op_num: SeqNumberType,
-        points: &Vec<PointIdType>,
-        keys: &Vec<PayloadKeyType>,
+        points: &[PointIdType],
+        keys: &[PayloadKeyType],
+        expected_versions: &Option<HashMap<PointIdType, SeqNumberType>>,
     ) -> OperationResult<usize> {
         let mut updated_points: HashSet<PointIdType> = Default::default();
-        let res = self.segments
-            .read().unwrap()
-            .apply_points(op_num, points, |id, write_segment| {
-                updated_points.insert(id);
-                let mut res = true;
-                for key in keys {
-                    res = write_segment.delete_payload(op_num, id, key)? && res;
-                }
-                Ok(res)
-            })?;
+        let res = SegmentHolder::apply_points(&self.segments, op_num, points, |id, write_segment| {
+            SimpleSegmentUpdater::check_expected_version(id, expected_versions, write_segment.point_version(id).unwrap_or(0))?;
+            updated_points.insert(id);
+            let mut res = true;
+            for key in keys {
+                res = write_segment.delete_payload(op_num, id, key)? && res;
+            }
+            Ok(res)
+        })?;
 
         SimpleSegmentUpdater::check_unprocessed_points(points, &updated_points)?;
         Ok(res)
@@ -116,15 +276,13 @@ impl SimpleSegmentUpdater {
     fn clear_payload(
         &self,
         op_num: SeqNumberType,
-        points: &Vec<PointIdType>,
+        points: &[PointIdType],
     ) -> OperationResult<usize> {
         let mut updated_points: HashSet<PointIdType> = Default::default();
-        let res = self.segments
-            .read().unwrap()
-            .apply_points(op_num, points, |id, write_segment| {
-                updated_points.insert(id);
-                write_segment.clear_payload(op_num, id)
-            })?;
+        let res = SegmentHolder::apply_points(&self.segments, op_num, points, |id, write_segment| {
+            updated_points.insert(id);
+            write_segment.clear_payload(op_num, id)
+        })?;
 
         SimpleSegmentUpdater::check_unprocessed_points(points, &updated_points)?;
         Ok(res)
@@ -134,7 +292,7 @@ impl SimpleSegmentUpdater {
         &self,
         op_num: SeqNumberType,
     ) -> OperationResult<usize> {
-        self.segments.read().unwrap().apply_segments(op_num, |segment| segment.wipe_payload(op_num))
+        SegmentHolder::apply_segments(&self.segments, op_num, |segment| segment.wipe_payload(op_num))
     }
 
     pub fn process_point_operation(&self, op_num: SeqNumberType, point_operation: &PointOps) -> OperationResult<usize> {
@@ -142,8 +300,9 @@ impl SimpleSegmentUpdater {
             PointOps::UpsertPoints {
                 ids,
                 vectors,
+                expected_versions,
                 ..
-            } => self.upsert_points(op_num, ids, vectors),
+            } => self.upsert_points(op_num, ids, vectors, expected_versions),
             PointOps::DeletePoints { ids, .. } => self.delete_points(op_num, ids),
         }
     }
@@ -154,13 +313,15 @@ impl SimpleSegmentUpdater {
             PayloadOps::SetPayload {
                 payload,
                 points,
+                expected_versions,
                 ..
-            } => self.set_payload(op_num, payload, points),
+            } => self.set_payload(op_num, payload, points, expected_versions),
             PayloadOps::DeletePayload {
                 keys,
                 points,
+                expected_versions,
                 ..
-            } => self.delete_payload(op_num, points, keys),
+            } => self.delete_payload(op_num, points, keys, expected_versions),
             PayloadOps::ClearPayload {
                 points, ..
             } => self.clear_payload(op_num, points),
@@ -172,9 +333,15 @@ impl SimpleSegmentUpdater {
 
 impl SegmentUpdater for SimpleSegmentUpdater {
     fn update(&self, op_num: SeqNumberType, operation: &CollectionUpdateOperations) -> OperationResult<usize> {
-        match operation {
-            CollectionUpdateOperations::PointOperation(point_operation) => self.process_point_operation(op_num, point_operation),
-            CollectionUpdateOperations::PayloadOperation(payload_operation) => self.process_payload_operation(op_num, payload_operation),
+        self.wal.append(op_num, operation)?;
+        self.apply(op_num, operation)
+    }
+}
+
+impl Drop for SimpleSegmentUpdater {
+    fn drop(&mut self) {
+        if let Some(handle) = self.merge_handle.take() {
+            handle.stop();
         }
     }
 }
@@ -183,16 +350,25 @@ impl SegmentUpdater for SimpleSegmentUpdater {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::operations::payload_ops::PayloadVariant;
     use crate::segment_manager::fixtures::{build_test_holder, build_searcher};
+    use crate::segment_manager::segment_holder::LockedSegment;
     use crate::segment_manager::segment_managers::SegmentSearcher;
+    use segment::simple_segment::SimpleSegment;
+    use std::sync::Barrier;
+    use std::thread;
 
     #[test]
     fn test_point_ops() {
         let searcher = build_searcher();
 
-        let updater = SimpleSegmentUpdater {
-            segments: searcher.segments.clone()
-        };
+        let wal_path = std::env::temp_dir().join(format!("test_point_ops_{:?}.wal", std::thread::current().id()));
+        let updater = SimpleSegmentUpdater::new(
+            searcher.segments.clone(),
+            MergeThresholds::default(),
+            1,
+            &wal_path,
+        ).unwrap();
         let points = vec![1, 500];
 
         let vectors = vec![
@@ -204,14 +380,18 @@ mod tests {
             100,
             &points,
             &vectors,
+            &None,
         );
 
+        // Point 1 already existed (seeded by `build_searcher`) and gets updated in
+        // place; point 500 is brand new and gets inserted -- both count towards the
+        // returned total.
         match res {
-            Ok(updated) => assert_eq!(updated, 1),
-            Err(_) => assert!(false),
+            Ok(updated) => assert_eq!(updated, 2),
+            Err(err) => panic!("expected the upsert to succeed, got {:?}", err),
         };
 
-        let records = searcher.retrieve(&vec![1, 2, 500], true, true);
+        let records = searcher.retrieve(&[1, 2, 500], true, true);
 
         assert_eq!(records.len(), 3);
 
@@ -226,16 +406,90 @@ mod tests {
             }
         }
 
-        updater.delete_points(101, &vec![500]);
+        updater.delete_points(101, &[500]).unwrap();
 
-        let records = searcher.retrieve(&vec![1, 2, 500], true, true);
+        let records = searcher.retrieve(&[1, 2, 500], true, true);
 
         for record in records {
-            let v = record.vector.unwrap();
+            assert_ne!(record.id, 500);
+        }
+    }
 
-            if record.id == 500 {
-                assert!(false)
-            }
+    #[test]
+    fn test_upsert_rejects_stale_expected_version() {
+        let searcher = build_searcher();
+
+        let wal_path = std::env::temp_dir().join(format!("test_version_conflict_{:?}.wal", std::thread::current().id()));
+        let updater = SimpleSegmentUpdater::new(
+            searcher.segments.clone(),
+            MergeThresholds::default(),
+            1,
+            &wal_path,
+        ).unwrap();
+
+        // Point 1 was seeded at version 1 (see `build_test_holder`); a stale caller
+        // expecting version 0 must be rejected rather than silently overwritten.
+        let mut expected_versions = HashMap::new();
+        expected_versions.insert(1, 0);
+        let res = updater.upsert_points(100, &[1], &[vec![9., 9., 9., 9.]], &Some(expected_versions));
+        match res {
+            Err(UpdateError::VersionConflict { point_id: 1, expected: 0, actual: 1 }) => {}
+            other => panic!("expected a version conflict for point 1, got {:?}", other),
+        }
+        let records = searcher.retrieve(&[1], false, true);
+        assert_eq!(records[0].vector.as_ref().unwrap(), &vec![1., 1., 1., 1.]);
+
+        // A brand-new point has never been written, so its "current version" is the 0
+        // sentinel -- a caller that (incorrectly) expects it to already be at version 1
+        // must also be rejected.
+        let mut expected_versions = HashMap::new();
+        expected_versions.insert(999, 1);
+        let res = updater.upsert_points(101, &[999], &[vec![9., 9., 9., 9.]], &Some(expected_versions));
+        match res {
+            Err(UpdateError::VersionConflict { point_id: 999, expected: 1, actual: 0 }) => {}
+            other => panic!("expected a version conflict for new point 999, got {:?}", other),
+        }
+        assert!(searcher.retrieve(&[999], false, false).is_empty());
+
+        // A correct expected_version for the new point (0) is accepted.
+        let mut expected_versions = HashMap::new();
+        expected_versions.insert(999, 0);
+        updater.upsert_points(102, &[999], &[vec![9., 9., 9., 9.]], &Some(expected_versions)).unwrap();
+        let records = searcher.retrieve(&[999], false, true);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].version, 102);
+    }
+
+    #[test]
+    fn test_set_payload_rejects_stale_expected_version() {
+        let searcher = build_searcher();
+
+        let wal_path = std::env::temp_dir().join(format!("test_payload_version_conflict_{:?}.wal", std::thread::current().id()));
+        let updater = SimpleSegmentUpdater::new(
+            searcher.segments.clone(),
+            MergeThresholds::default(),
+            1,
+            &wal_path,
+        ).unwrap();
+
+        let mut payload: HashMap<PayloadKeyType, PayloadInterface> = Default::default();
+        payload.insert("color".to_string(), PayloadInterface::Keyword(PayloadVariant::Value("red".to_string())));
+
+        // Point 1 was seeded at version 1 (see `build_test_holder`). The first payload
+        // write correctly expects version 1 and succeeds.
+        let mut expected_versions = HashMap::new();
+        expected_versions.insert(1, 1);
+        updater.set_payload(100, &payload, &[1], &Some(expected_versions)).unwrap();
+
+        // A second payload write still expecting the pre-write version 1 must be
+        // rejected: if `set_payload` didn't bump `point_versions`, this would silently
+        // succeed and clobber the first caller's edit.
+        let mut expected_versions = HashMap::new();
+        expected_versions.insert(1, 1);
+        let res = updater.set_payload(101, &payload, &[1], &Some(expected_versions));
+        match res {
+            Err(UpdateError::VersionConflict { point_id: 1, expected: 1, actual: 100 }) => {}
+            other => panic!("expected a version conflict for point 1, got {:?}", other),
         }
     }
 
@@ -243,9 +497,13 @@ mod tests {
     fn test_payload_ops() {
         let searcher = build_searcher();
 
-        let updater = SimpleSegmentUpdater {
-            segments: searcher.segments.clone()
-        };
+        let wal_path = std::env::temp_dir().join(format!("test_payload_ops_{:?}.wal", std::thread::current().id()));
+        let updater = SimpleSegmentUpdater::new(
+            searcher.segments.clone(),
+            MergeThresholds::default(),
+            1,
+            &wal_path,
+        ).unwrap();
 
         let mut payload: HashMap<PayloadKeyType, PayloadInterface> = Default::default();
 
@@ -260,38 +518,170 @@ mod tests {
             collection: "".to_string(),
             payload,
             points: points.clone(),
-        });
+            expected_versions: None,
+        }).unwrap();
 
         let res = searcher.retrieve(&points, true, false);
 
         assert_eq!(res.len(), 3);
 
-        match res.get(0) {
-            None => assert!(false),
+        match res.first() {
+            None => panic!("expected a record for one of the upserted points"),
             Some(r) => match &r.payload {
-                None => assert!(false, "No payload assigned"),
+                None => panic!("No payload assigned"),
                 Some(payload) => {
                     assert!(payload.contains_key("color"))
                 }
             },
         };
 
-        /// Test payload delete
+        // Test payload delete
 
-        updater.delete_payload(101, &vec![3], &vec!["color".to_string(), "empty".to_string()]);
-        let res = searcher.retrieve(&vec![3], true, false);
+        updater.delete_payload(101, &[3], &["color".to_string(), "empty".to_string()], &None).unwrap();
+        let res = searcher.retrieve(&[3], true, false);
         assert_eq!(res.len(), 1);
         assert!(!res[0].payload.as_ref().unwrap().contains_key("color"));
 
-        /// Test clear payload
+        // Test clear payload
 
-        let res = searcher.retrieve(&vec![2], true, false);
+        let res = searcher.retrieve(&[2], true, false);
         assert_eq!(res.len(), 1);
         assert!(res[0].payload.as_ref().unwrap().contains_key("color"));
 
-        updater.clear_payload(102, &vec![2]);
-        let res = searcher.retrieve(&vec![2], true, false);
+        updater.clear_payload(102, &[2]).unwrap();
+        let res = searcher.retrieve(&[2], true, false);
         assert_eq!(res.len(), 1);
-        assert!(!res[0].payload.as_ref().unwrap().contains_key("color"))
+        assert!(res[0].payload.is_none())
+    }
+
+    #[test]
+    fn test_recovers_state_after_restart() {
+        let wal_path = std::env::temp_dir().join(format!("test_restart_recovery_{:?}.wal", std::thread::current().id()));
+        let _ = std::fs::remove_file(&wal_path);
+
+        {
+            let updater = SimpleSegmentUpdater::new(
+                build_test_holder(),
+                MergeThresholds::default(),
+                1,
+                &wal_path,
+            ).unwrap();
+            updater.update(100, &CollectionUpdateOperations::PointOperation(PointOps::UpsertPoints {
+                collection: "".to_string(),
+                ids: vec![999],
+                vectors: vec![vec![9., 9., 9., 9.]],
+                expected_versions: None,
+            })).unwrap();
+        } // updater dropped here -- simulates the process stopping before a checkpoint.
+
+        // A fresh holder stands in for segments as they existed on disk before the
+        // restart (i.e. without the write above): `new()` must replay the WAL entry
+        // whose op_num (100) is beyond what that holder's segment already reflects.
+        let segments = build_test_holder();
+        let searcher_segments = segments.clone();
+        let updater = SimpleSegmentUpdater::new(
+            segments,
+            MergeThresholds::default(),
+            1,
+            &wal_path,
+        ).unwrap();
+
+        let searcher = crate::segment_manager::segment_managers::SimpleSegmentSearcher::new(searcher_segments);
+        let records = searcher.retrieve(&[999], false, true);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].vector.as_ref().unwrap(), &vec![9., 9., 9., 9.]);
+
+        drop(updater);
+        std::fs::remove_file(&wal_path).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_recovers_after_rejected_op_logged_to_wal() {
+        let wal_path = std::env::temp_dir().join(format!("test_rejected_op_recovery_{:?}.wal", std::thread::current().id()));
+        let _ = std::fs::remove_file(&wal_path);
+
+        {
+            let updater = SimpleSegmentUpdater::new(
+                build_test_holder(),
+                MergeThresholds::default(),
+                1,
+                &wal_path,
+            ).unwrap();
+
+            // SetPayload against a point id that doesn't exist anywhere is logged to the
+            // WAL before it's applied, so it's durable even though it's rejected.
+            let mut payload: HashMap<PayloadKeyType, PayloadInterface> = Default::default();
+            payload.insert("color".to_string(), PayloadInterface::Keyword(PayloadVariant::Value("red".to_string())));
+            let res = updater.update(100, &CollectionUpdateOperations::PayloadOperation(PayloadOps::SetPayload {
+                collection: "".to_string(),
+                payload,
+                points: vec![999],
+                expected_versions: None,
+            }));
+            match res {
+                Err(UpdateError::NotFound { missed_point_id: 999 }) => {}
+                other => panic!("expected NotFound for point 999, got {:?}", other),
+            }
+        } // updater dropped here -- the rejected op is still sitting in the WAL.
+
+        // Constructing a fresh updater over the same WAL must not fail: replaying the
+        // logged-but-rejected op should reproduce the same rejection and move on rather
+        // than aborting construction.
+        let updater = SimpleSegmentUpdater::new(
+            build_test_holder(),
+            MergeThresholds::default(),
+            1,
+            &wal_path,
+        ).unwrap();
+
+        drop(updater);
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_upsert_of_new_point_does_not_duplicate_across_segments() {
+        let mut holder = SegmentHolder::new();
+        holder.add_segment(LockedSegment::new(SimpleSegment::new()));
+        holder.add_segment(LockedSegment::new(SimpleSegment::new()));
+        let segments = Arc::new(RwLock::new(holder));
+
+        let wal_path = std::env::temp_dir().join(format!("test_concurrent_new_point_{:?}.wal", std::thread::current().id()));
+        let updater = Arc::new(SimpleSegmentUpdater::new(
+            segments.clone(),
+            MergeThresholds::default(),
+            1,
+            &wal_path,
+        ).unwrap());
+
+        // Two callers racing to "create" the same brand-new point id, each with two
+        // segments to choose from -- if the new-point decision weren't serialized
+        // process-wide, they could each conclude it's absent and land it in a
+        // different segment.
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = [100, 101]
+            .into_iter()
+            .map(|op_num| {
+                let updater = updater.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    updater.upsert_points(op_num, &[999], &[vec![1., 1., 1., 1.]], &None)
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+
+        let copies = segments
+            .read().unwrap()
+            .iter()
+            .filter(|(_, segment)| segment.get().read().unwrap().point_version(999).is_some())
+            .count();
+        assert_eq!(copies, 1, "point 999 must land in exactly one segment, not be duplicated");
+
+        drop(updater);
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+}
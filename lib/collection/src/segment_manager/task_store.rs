@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::collection::{OperationResult, UpdateError};
+use crate::operations::CollectionUpdateOperations;
+
+pub type TaskId = u64;
+
+/// Lifecycle of a task submitted through [`AsyncSegmentUpdater`](super::async_updater::AsyncSegmentUpdater).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TaskStatus {
+    Enqueued,
+    Processing,
+    Succeeded { updated: usize },
+    Failed { error: String },
+}
+
+/// Status without its payload, so callers can filter tasks by what happened to them
+/// without caring about the details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatusKind {
+    Enqueued,
+    Processing,
+    Succeeded,
+    Failed,
+}
+
+impl TaskStatus {
+    fn kind(&self) -> TaskStatusKind {
+        match self {
+            TaskStatus::Enqueued => TaskStatusKind::Enqueued,
+            TaskStatus::Processing => TaskStatusKind::Processing,
+            TaskStatus::Succeeded { .. } => TaskStatusKind::Succeeded,
+            TaskStatus::Failed { .. } => TaskStatusKind::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: TaskId,
+    pub operation: CollectionUpdateOperations,
+    pub status: TaskStatus,
+}
+
+/// Criteria for [`TaskStore::list`]: tasks must match every `Some` field to be included.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub status: Option<TaskStatusKind>,
+    pub since_task_id: Option<TaskId>,
+}
+
+fn service_error(context: &str, error: impl std::fmt::Display) -> UpdateError {
+    UpdateError::ServiceError { error: format!("{}: {}", context, error) }
+}
+
+/// Durable store of task metadata, so `get_task`/`list_tasks` keep working across a
+/// restart even while tasks are still queued or processing. Persists as a single JSON
+/// file, rewritten on every status transition -- task volume is expected to be small
+/// relative to point volume, so this is not on the hot path.
+pub struct TaskStore {
+    path: PathBuf,
+    tasks: Mutex<BTreeMap<TaskId, Task>>,
+}
+
+impl TaskStore {
+    pub fn open(path: impl AsRef<Path>) -> OperationResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut tasks: BTreeMap<TaskId, Task> = if path.exists() {
+            let contents = fs::read_to_string(&path).map_err(|err| service_error("failed to read task store", err))?;
+            serde_json::from_str(&contents).map_err(|err| service_error("failed to parse task store", err))?
+        } else {
+            BTreeMap::new()
+        };
+
+        // A task stuck at `Processing` was being worked on when the process crashed --
+        // there's no worker left running it, so without this it would sit reported as
+        // "in progress" forever. Reset it to `Enqueued` so `pending_ids()` picks it back
+        // up and a fresh worker re-runs it (safe: segment application is idempotent by
+        // op_num, same as any other WAL/task replay in this module).
+        let mut needs_persist = false;
+        for task in tasks.values_mut() {
+            if task.status == TaskStatus::Processing {
+                task.status = TaskStatus::Enqueued;
+                needs_persist = true;
+            }
+        }
+
+        let store = TaskStore { path, tasks: Mutex::new(tasks) };
+        if needs_persist {
+            store.persist(&store.tasks.lock().unwrap())?;
+        }
+        Ok(store)
+    }
+
+    /// Writes via a tmp-file-then-rename, mirroring `wal.rs::checkpoint` -- a crash
+    /// mid-write leaves the tmp file truncated but the rename is atomic, so `self.path`
+    /// itself never observes a partial write that `open()` would fail to parse.
+    fn persist(&self, tasks: &BTreeMap<TaskId, Task>) -> OperationResult<()> {
+        let contents = serde_json::to_string(tasks).map_err(|err| service_error("failed to serialize task store", err))?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, contents).map_err(|err| service_error("failed to write task store", err))?;
+        fs::rename(&tmp_path, &self.path).map_err(|err| service_error("failed to install task store", err))
+    }
+
+    pub fn enqueue(&self, id: TaskId, operation: CollectionUpdateOperations) -> OperationResult<()> {
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.insert(id, Task { id, operation, status: TaskStatus::Enqueued });
+        self.persist(&tasks)
+    }
+
+    pub fn set_status(&self, id: TaskId, status: TaskStatus) -> OperationResult<()> {
+        let mut tasks = self.tasks.lock().unwrap();
+        if let Some(task) = tasks.get_mut(&id) {
+            task.status = status;
+        }
+        self.persist(&tasks)
+    }
+
+    pub fn get(&self, id: TaskId) -> Option<Task> {
+        self.tasks.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn list(&self, filter: &TaskFilter) -> Vec<Task> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|task| filter.status.is_none_or(|kind| task.status.kind() == kind))
+            .filter(|task| filter.since_task_id.is_none_or(|since| task.id >= since))
+            .cloned()
+            .collect()
+    }
+
+    /// Ids of tasks that were still `Enqueued` the last time status was persisted --
+    /// used to resume a worker's queue after a restart.
+    pub fn pending_ids(&self) -> Vec<TaskId> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|task| task.status == TaskStatus::Enqueued)
+            .map(|task| task.id)
+            .collect()
+    }
+
+    /// Highest id across every persisted task, regardless of status -- used to derive
+    /// the next id to hand out after a restart. `pending_ids()` alone isn't enough: if
+    /// nothing is `Enqueued` (the common case, everything finished before the crash),
+    /// it would say nothing, and a caller falling back to "start over at 1" would reuse
+    /// ids, and therefore op_nums, that are already stamped on existing points.
+    pub fn max_task_id(&self) -> Option<TaskId> {
+        self.tasks.lock().unwrap().keys().next_back().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::point_ops::PointOps;
+
+    fn delete_op(id: u64) -> CollectionUpdateOperations {
+        CollectionUpdateOperations::PointOperation(PointOps::DeletePoints {
+            collection: "".to_string(),
+            ids: vec![id],
+        })
+    }
+
+    #[test]
+    fn test_open_resumes_tasks_stuck_at_processing() {
+        let path = std::env::temp_dir().join(format!("task_store_test_resume_{:?}.json", std::thread::current().id()));
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let store = TaskStore::open(&path).unwrap();
+            store.enqueue(1, delete_op(1)).unwrap();
+            // Simulates a crash after the worker picked the task up but before it
+            // finished: nothing is left to resume it, so without this, `get_task` would
+            // report it as permanently in progress.
+            store.set_status(1, TaskStatus::Processing).unwrap();
+        }
+
+        let store = TaskStore::open(&path).unwrap();
+        assert_eq!(store.get(1).unwrap().status, TaskStatus::Enqueued);
+        assert_eq!(store.pending_ids(), vec![1]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
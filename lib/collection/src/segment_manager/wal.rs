@@ -0,0 +1,227 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crc32fast::Hasher;
+use serde::{Deserialize, Serialize};
+
+use crate::collection::{OperationResult, UpdateError};
+use crate::operations::CollectionUpdateOperations;
+use segment::types::SeqNumberType;
+
+/// One durable entry in the write-ahead log: an operation together with the `op_num`
+/// it was assigned, so replay can tell whether a segment already reflects it.
+#[derive(Serialize, Deserialize)]
+struct WalRecord {
+    op_num: SeqNumberType,
+    operation: CollectionUpdateOperations,
+}
+
+fn service_error(context: &str, error: impl std::fmt::Display) -> UpdateError {
+    UpdateError::ServiceError { error: format!("{}: {}", context, error) }
+}
+
+/// Append-only, length-prefixed and CRC-checked log of update operations. Sits in front
+/// of `SegmentUpdater::update` so a crash between applying an operation and flushing
+/// segments can be recovered from by replaying the tail of the log.
+pub struct Wal {
+    writer: Mutex<BufWriter<std::fs::File>>,
+    path: PathBuf,
+}
+
+impl Wal {
+    pub fn open(path: impl AsRef<Path>) -> OperationResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|err| service_error("failed to open WAL", err))?;
+        Ok(Wal { writer: Mutex::new(BufWriter::new(file)), path })
+    }
+
+    /// Appends `operation` tagged with `op_num` and flushes it to disk, so a successful
+    /// return means the operation is durably recorded even if it hasn't been applied to
+    /// any segment yet.
+    pub fn append(&self, op_num: SeqNumberType, operation: &CollectionUpdateOperations) -> OperationResult<()> {
+        let record = WalRecord { op_num, operation: operation.clone() };
+        let payload = bincode::serialize(&record).map_err(|err| service_error("failed to serialize WAL record", err))?;
+
+        let mut hasher = Hasher::new();
+        hasher.update(&payload);
+        let crc = hasher.finalize();
+
+        let mut writer = self.writer.lock().unwrap();
+        writer
+            .write_all(&(payload.len() as u32).to_le_bytes())
+            .and_then(|_| writer.write_all(&payload))
+            .and_then(|_| writer.write_all(&crc.to_le_bytes()))
+            .and_then(|_| writer.flush())
+            .map_err(|err| service_error("failed to append WAL record", err))
+    }
+
+    /// Reads every record currently in the log, in the order they were appended.
+    pub fn read_all(&self) -> OperationResult<Vec<(SeqNumberType, CollectionUpdateOperations)>> {
+        let file = std::fs::File::open(&self.path).map_err(|err| service_error("failed to open WAL", err))?;
+        let mut reader = BufReader::new(file);
+        let mut records = Vec::new();
+
+        loop {
+            let mut len_buf = [0u8; 4];
+            match reader.read_exact(&mut len_buf) {
+                Ok(()) => {}
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(service_error("failed to read WAL length prefix", err)),
+            }
+            let len = u32::from_le_bytes(len_buf) as usize;
+
+            let mut payload = vec![0u8; len];
+            reader
+                .read_exact(&mut payload)
+                .map_err(|err| service_error("truncated WAL record", err))?;
+
+            let mut crc_buf = [0u8; 4];
+            reader
+                .read_exact(&mut crc_buf)
+                .map_err(|err| service_error("truncated WAL record", err))?;
+            let expected_crc = u32::from_le_bytes(crc_buf);
+
+            let mut hasher = Hasher::new();
+            hasher.update(&payload);
+            if hasher.finalize() != expected_crc {
+                return Err(UpdateError::ServiceError { error: "WAL record failed CRC check, log appears corrupted".to_string() });
+            }
+
+            let record: WalRecord =
+                bincode::deserialize(&payload).map_err(|err| service_error("failed to deserialize WAL record", err))?;
+            records.push((record.op_num, record.operation));
+        }
+
+        Ok(records)
+    }
+
+    /// Drops every record with `op_num <= min_flushed_op_num` — entries already durable
+    /// in every segment and therefore no longer needed for recovery. Holds the writer
+    /// lock for the entire read -> filter -> write-tmp -> rename sequence: `append()`
+    /// takes the same lock, so a concurrent append can't write into the file we're
+    /// about to replace and have that record silently discarded by the rename.
+    pub fn checkpoint(&self, min_flushed_op_num: SeqNumberType) -> OperationResult<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.flush().map_err(|err| service_error("failed to flush WAL before checkpoint", err))?;
+
+        let keep: Vec<_> = self
+            .read_all()?
+            .into_iter()
+            .filter(|(op_num, _)| *op_num > min_flushed_op_num)
+            .collect();
+
+        let tmp_path = self.path.with_extension("wal.tmp");
+        {
+            let tmp_file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp_path)
+                .map_err(|err| service_error("failed to create WAL checkpoint file", err))?;
+            let mut tmp_writer = BufWriter::new(tmp_file);
+            for (op_num, operation) in &keep {
+                let record = WalRecord { op_num: *op_num, operation: operation.clone() };
+                let payload =
+                    bincode::serialize(&record).map_err(|err| service_error("failed to serialize WAL record", err))?;
+                let mut hasher = Hasher::new();
+                hasher.update(&payload);
+                let crc = hasher.finalize();
+                tmp_writer
+                    .write_all(&(payload.len() as u32).to_le_bytes())
+                    .and_then(|_| tmp_writer.write_all(&payload))
+                    .and_then(|_| tmp_writer.write_all(&crc.to_le_bytes()))
+                    .map_err(|err| service_error("failed to write WAL checkpoint", err))?;
+            }
+            tmp_writer.flush().map_err(|err| service_error("failed to flush WAL checkpoint", err))?;
+        }
+
+        std::fs::rename(&tmp_path, &self.path).map_err(|err| service_error("failed to install WAL checkpoint", err))?;
+
+        let reopened = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(|err| service_error("failed to reopen WAL after checkpoint", err))?;
+        *writer = BufWriter::new(reopened);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Seek, SeekFrom};
+    use crate::operations::point_ops::PointOps;
+
+    fn delete_op(id: u64) -> CollectionUpdateOperations {
+        CollectionUpdateOperations::PointOperation(PointOps::DeletePoints {
+            collection: "".to_string(),
+            ids: vec![id],
+        })
+    }
+
+    #[test]
+    fn test_append_and_read_all_round_trips() {
+        let path = std::env::temp_dir().join(format!("wal_test_round_trip_{:?}.wal", std::thread::current().id()));
+        let _ = std::fs::remove_file(&path);
+
+        let wal = Wal::open(&path).unwrap();
+        wal.append(1, &delete_op(1)).unwrap();
+        wal.append(2, &delete_op(2)).unwrap();
+
+        let records = wal.read_all().unwrap();
+        assert_eq!(records.iter().map(|(op_num, _)| *op_num).collect::<Vec<_>>(), vec![1, 2]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_drops_flushed_entries_and_keeps_the_rest() {
+        let path = std::env::temp_dir().join(format!("wal_test_checkpoint_{:?}.wal", std::thread::current().id()));
+        let _ = std::fs::remove_file(&path);
+
+        let wal = Wal::open(&path).unwrap();
+        wal.append(1, &delete_op(1)).unwrap();
+        wal.append(2, &delete_op(2)).unwrap();
+        wal.append(3, &delete_op(3)).unwrap();
+
+        wal.checkpoint(1).unwrap();
+
+        let records = wal.read_all().unwrap();
+        assert_eq!(records.iter().map(|(op_num, _)| *op_num).collect::<Vec<_>>(), vec![2, 3]);
+
+        // The WAL must still be appendable after a checkpoint has rotated its file.
+        wal.append(4, &delete_op(4)).unwrap();
+        let records = wal.read_all().unwrap();
+        assert_eq!(records.iter().map(|(op_num, _)| *op_num).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_all_rejects_corrupted_record() {
+        let path = std::env::temp_dir().join(format!("wal_test_corruption_{:?}.wal", std::thread::current().id()));
+        let _ = std::fs::remove_file(&path);
+
+        let wal = Wal::open(&path).unwrap();
+        wal.append(1, &delete_op(1)).unwrap();
+
+        // Flip a byte in the middle of the file to corrupt the payload without
+        // otherwise changing the record's framing.
+        {
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.seek(SeekFrom::Start(6)).unwrap();
+            file.write_all(&[0xFF]).unwrap();
+        }
+
+        let err = wal.read_all().unwrap_err();
+        assert!(matches!(err, UpdateError::ServiceError { .. }));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use crate::types::{PayloadKeyType, PayloadType, PointIdType, SeqNumberType, TheMap, VectorElementType};
+
+#[derive(Debug)]
+pub enum OperationError {
+    PointIdError { missed_point_id: PointIdType },
+    ServiceError { description: String },
+    /// A caller-supplied `expected_version` for `point_id` didn't match its current
+    /// version, so the mutation was rejected rather than silently overwriting a
+    /// concurrent change.
+    VersionConflict { point_id: PointIdType, expected: SeqNumberType, actual: SeqNumberType },
+}
+
+impl fmt::Display for OperationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OperationError::PointIdError { missed_point_id } => write!(f, "point {} not found", missed_point_id),
+            OperationError::ServiceError { description } => write!(f, "service error: {}", description),
+            OperationError::VersionConflict { point_id, expected, actual } => write!(
+                f,
+                "point {} has version {} but expected {}",
+                point_id, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OperationError {}
+
+pub type Result<T> = std::result::Result<T, OperationError>;
+
+/// Interface every concrete segment implementation exposes to the collection layer,
+/// independent of storage/indexing strategy.
+pub trait SegmentEntry: Send + Sync {
+    /// Highest op_num applied to this segment so far.
+    fn version(&self) -> SeqNumberType;
+
+    /// Raises `version()` to at least `op_num`, without touching any point. Used after
+    /// building a segment from other segments' contents (e.g. a merge), so the result
+    /// carries forward the true max op_num among its inputs even when that max came from
+    /// an op (a payload-only edit, a tombstoned point never copied across) that left no
+    /// other trace in the rebuilt segment.
+    fn bump_version(&mut self, op_num: SeqNumberType);
+
+    /// Seq number `point_id` was last written with, doubling as its MVCC version.
+    /// `None` if the point doesn't exist in this segment.
+    fn point_version(&self, point_id: PointIdType) -> Option<SeqNumberType>;
+
+    fn upsert_point(&mut self, op_num: SeqNumberType, point_id: PointIdType, vector: &[VectorElementType]) -> Result<bool>;
+    fn delete_point(&mut self, op_num: SeqNumberType, point_id: PointIdType) -> Result<bool>;
+    fn set_payload(&mut self, op_num: SeqNumberType, point_id: PointIdType, key: &PayloadKeyType, value: PayloadType) -> Result<bool>;
+    fn delete_payload(&mut self, op_num: SeqNumberType, point_id: PointIdType, key: &PayloadKeyType) -> Result<bool>;
+    fn clear_payload(&mut self, op_num: SeqNumberType, point_id: PointIdType) -> Result<bool>;
+    fn wipe_payload(&mut self, op_num: SeqNumberType) -> Result<usize>;
+
+    fn vector(&self, point_id: PointIdType) -> Result<Vec<VectorElementType>>;
+    fn payload(&self, point_id: PointIdType) -> Result<Option<TheMap<PayloadKeyType, PayloadType>>>;
+
+    fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_>;
+    fn points_count(&self) -> usize;
+
+    /// Persists this segment's state and returns the op_num that is now durable.
+    fn flush(&self) -> Result<SeqNumberType>;
+}
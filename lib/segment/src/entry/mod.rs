@@ -0,0 +1 @@
+pub mod entry_point;
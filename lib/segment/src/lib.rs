@@ -0,0 +1,3 @@
+pub mod entry;
+pub mod simple_segment;
+pub mod types;
@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::entry::entry_point::{OperationError, Result, SegmentEntry};
+use crate::types::{PayloadKeyType, PayloadType, PointIdType, SeqNumberType, TheMap, VectorElementType};
+
+/// Minimal in-memory `SegmentEntry` implementation used by tests and fixtures. Applies
+/// the same idempotency rule a real, indexed segment does: an operation whose `op_num`
+/// is not newer than the point's current version is a no-op, so WAL replay is safe.
+#[derive(Default)]
+pub struct SimpleSegment {
+    version: SeqNumberType,
+    vectors: HashMap<PointIdType, Vec<VectorElementType>>,
+    payload: HashMap<PointIdType, TheMap<PayloadKeyType, PayloadType>>,
+    point_versions: HashMap<PointIdType, SeqNumberType>,
+    deleted: HashSet<PointIdType>,
+}
+
+impl SimpleSegment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn require_point(&self, point_id: PointIdType) -> Result<()> {
+        if self.vectors.contains_key(&point_id) {
+            Ok(())
+        } else {
+            Err(OperationError::PointIdError { missed_point_id: point_id })
+        }
+    }
+
+    /// Advances both the point's own version and the segment-wide version to `op_num`,
+    /// the same way `upsert_point`/`delete_point` do. Payload edits must bump
+    /// `point_versions` too, not just `version` -- `point_version()` is what
+    /// `expected_version` checks compare against, so leaving it untouched here would
+    /// let two concurrent payload writes against the same stale expected version both
+    /// succeed instead of the second being rejected as a conflict.
+    fn bump_point_version(&mut self, point_id: PointIdType, op_num: SeqNumberType) {
+        let current = self.point_versions.get(&point_id).copied().unwrap_or(0);
+        self.point_versions.insert(point_id, current.max(op_num));
+        self.version = self.version.max(op_num);
+    }
+}
+
+impl SegmentEntry for SimpleSegment {
+    fn version(&self) -> SeqNumberType {
+        self.version
+    }
+
+    fn bump_version(&mut self, op_num: SeqNumberType) {
+        self.version = self.version.max(op_num);
+    }
+
+    fn point_version(&self, point_id: PointIdType) -> Option<SeqNumberType> {
+        if self.deleted.contains(&point_id) {
+            return None;
+        }
+        self.point_versions.get(&point_id).copied()
+    }
+
+    fn upsert_point(&mut self, op_num: SeqNumberType, point_id: PointIdType, vector: &[VectorElementType]) -> Result<bool> {
+        if op_num <= self.point_versions.get(&point_id).copied().unwrap_or(0) {
+            return Ok(false);
+        }
+        self.deleted.remove(&point_id);
+        self.vectors.insert(point_id, vector.to_vec());
+        self.point_versions.insert(point_id, op_num);
+        self.version = self.version.max(op_num);
+        Ok(true)
+    }
+
+    fn delete_point(&mut self, op_num: SeqNumberType, point_id: PointIdType) -> Result<bool> {
+        if !self.vectors.contains_key(&point_id) {
+            return Ok(false);
+        }
+        if op_num <= self.point_versions.get(&point_id).copied().unwrap_or(0) {
+            return Ok(false);
+        }
+        self.deleted.insert(point_id);
+        self.vectors.remove(&point_id);
+        self.payload.remove(&point_id);
+        self.point_versions.insert(point_id, op_num);
+        self.version = self.version.max(op_num);
+        Ok(true)
+    }
+
+    fn set_payload(&mut self, op_num: SeqNumberType, point_id: PointIdType, key: &PayloadKeyType, value: PayloadType) -> Result<bool> {
+        self.require_point(point_id)?;
+        self.payload.entry(point_id).or_default().insert(key.clone(), value);
+        self.bump_point_version(point_id, op_num);
+        Ok(true)
+    }
+
+    fn delete_payload(&mut self, op_num: SeqNumberType, point_id: PointIdType, key: &PayloadKeyType) -> Result<bool> {
+        self.require_point(point_id)?;
+        let removed = self.payload.get_mut(&point_id).is_some_and(|p| p.remove(key).is_some());
+        self.bump_point_version(point_id, op_num);
+        Ok(removed)
+    }
+
+    fn clear_payload(&mut self, op_num: SeqNumberType, point_id: PointIdType) -> Result<bool> {
+        self.require_point(point_id)?;
+        let had_payload = self.payload.remove(&point_id).is_some();
+        self.bump_point_version(point_id, op_num);
+        Ok(had_payload)
+    }
+
+    fn wipe_payload(&mut self, op_num: SeqNumberType) -> Result<usize> {
+        let count = self.payload.len();
+        self.payload.clear();
+        self.version = self.version.max(op_num);
+        Ok(count)
+    }
+
+    fn vector(&self, point_id: PointIdType) -> Result<Vec<VectorElementType>> {
+        self.vectors
+            .get(&point_id)
+            .cloned()
+            .ok_or(OperationError::PointIdError { missed_point_id: point_id })
+    }
+
+    fn payload(&self, point_id: PointIdType) -> Result<Option<TheMap<PayloadKeyType, PayloadType>>> {
+        self.require_point(point_id)?;
+        Ok(self.payload.get(&point_id).cloned())
+    }
+
+    fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
+        Box::new(self.vectors.keys().copied())
+    }
+
+    fn points_count(&self) -> usize {
+        self.vectors.len()
+    }
+
+    fn flush(&self) -> Result<SeqNumberType> {
+        Ok(self.version)
+    }
+}
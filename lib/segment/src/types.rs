@@ -0,0 +1,17 @@
+use std::collections::HashMap;
+
+pub type SeqNumberType = u64;
+pub type PointIdType = u64;
+pub type PayloadKeyType = String;
+pub type VectorElementType = f64;
+
+pub type TheMap<K, V> = HashMap<K, V>;
+
+/// Value stored under a payload key. Always a vector internally so a single value and
+/// a list of values share the same representation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PayloadType {
+    Keyword(Vec<String>),
+    Integer(Vec<i64>),
+    Float(Vec<f64>),
+}